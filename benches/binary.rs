@@ -39,7 +39,7 @@ fn binary(c: &mut Criterion) {
                 let parsed = TestAnalytics::parse(&buf, 0).unwrap();
                 for test in parsed.tests() {
                     let _name = black_box(test.name().unwrap());
-                    let _aggregates = black_box(test.get_aggregates(0..60));
+                    let _aggregates = black_box(test.get_aggregates(0..60, None));
                 }
             })
         })
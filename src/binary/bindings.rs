@@ -5,7 +5,8 @@ use pyo3::prelude::*;
 
 use crate::Testrun;
 
-use super::{TestAnalytics, TestAnalyticsWriter};
+use super::format::Aggregates;
+use super::{RankMetric, Test, TestAnalytics, TestAnalyticsWriter};
 
 #[pyclass]
 pub struct BinaryFormatWriter {
@@ -57,7 +58,51 @@ pub struct AggregationReader {
 
 #[pyclass]
 pub struct TestAggregate {
-    // TODO
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get)]
+    pub testsuite: String,
+
+    #[pyo3(get)]
+    pub total_pass_count: u32,
+    #[pyo3(get)]
+    pub total_fail_count: u32,
+    #[pyo3(get)]
+    pub total_skip_count: u32,
+    #[pyo3(get)]
+    pub total_flaky_fail_count: u32,
+
+    #[pyo3(get)]
+    pub failure_rate: f32,
+    #[pyo3(get)]
+    pub flake_rate: f32,
+
+    #[pyo3(get)]
+    pub avg_duration: f64,
+}
+
+fn to_test_aggregate(test: &Test, data: Aggregates) -> anyhow::Result<TestAggregate> {
+    Ok(TestAggregate {
+        name: test.name()?.to_owned(),
+        testsuite: test.testsuite()?.to_owned(),
+        total_pass_count: data.total_pass_count,
+        total_fail_count: data.total_fail_count,
+        total_skip_count: data.total_skip_count,
+        total_flaky_fail_count: data.total_flaky_fail_count,
+        failure_rate: data.failure_rate,
+        flake_rate: data.flake_rate,
+        avg_duration: data.avg_duration,
+    })
+}
+
+fn rank_metric_from_str(metric: &str) -> anyhow::Result<RankMetric> {
+    match metric {
+        "failure_rate" => Ok(RankMetric::FailureRate),
+        "flake_rate" => Ok(RankMetric::FlakeRate),
+        "avg_duration" => Ok(RankMetric::AvgDuration),
+        "fail_count" => Ok(RankMetric::FailCount),
+        other => anyhow::bail!("unknown rank metric: {other}"),
+    }
 }
 
 #[pymethods]
@@ -78,7 +123,42 @@ impl AggregationReader {
         interval_start: usize,
         interval_end: usize,
         flag: Option<&str>,
-    ) -> Vec<TestAggregate> {
-        vec![]
+    ) -> anyhow::Result<Vec<TestAggregate>> {
+        let mut aggregates = Vec::new();
+
+        for test in self.format.tests() {
+            if let Some(flag) = flag {
+                if test.flag()? != Some(flag) {
+                    continue;
+                }
+            }
+
+            let data = test.get_aggregates(interval_start..interval_end, None);
+            aggregates.push(to_test_aggregate(&test, data)?);
+        }
+
+        Ok(aggregates)
+    }
+
+    /// Returns the `k` tests with the highest `metric` over the given interval.
+    ///
+    /// `metric` is one of `"failure_rate"`, `"flake_rate"`, `"avg_duration"`, or `"fail_count"`.
+    /// Tests are returned in descending order of `metric`, e.g. to power a "flakiest tests this
+    /// week" dashboard without pulling every test's aggregates into Python first.
+    #[pyo3(signature = (metric, interval_start, interval_end, k))]
+    pub fn top_tests(
+        &self,
+        metric: &str,
+        interval_start: usize,
+        interval_end: usize,
+        k: usize,
+    ) -> anyhow::Result<Vec<TestAggregate>> {
+        let metric = rank_metric_from_str(metric)?;
+
+        self.format
+            .top_tests(metric, interval_start..interval_end, k)
+            .into_iter()
+            .map(|(test, data)| to_test_aggregate(&test, data))
+            .collect()
     }
 }
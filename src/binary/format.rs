@@ -1,4 +1,9 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fmt;
+use std::io::Write;
+use std::mem;
 use std::ops::Range;
 
 use timestamps::{adjust_selection_range, offset_from_today};
@@ -7,91 +12,327 @@ use watto::{align_to, Pod};
 use super::*;
 
 /// The current format version.
-pub(crate) const TA_VERSION: u32 = 1;
+pub(crate) const TA_VERSION: u32 = 6;
+
+/// The number of log2-scaled duration histogram buckets recorded per test per day.
+///
+/// Bucket `0` covers zero/sub-millisecond durations, and bucket `i >= 1` covers
+/// `[2^(i-1), 2^i)` milliseconds. 32 buckets comfortably cover durations up to multiple days.
+pub(crate) const NUM_DURATION_BUCKETS: usize = 32;
 
 /// The serialized [`TestAnalytics`] binary format.
 ///
 /// This can be parsed from a binary buffer via [`TestAnalytics::parse`].
 #[derive(Clone, PartialEq)]
 pub struct TestAnalytics<'data> {
-    pub(crate) header: &'data raw::Header,
+    pub(crate) header: raw::Header,
     pub(crate) tests: &'data [raw::Test],
+    /// Indices into `tests`, sorted by the UTF-8 bytes of each test's name, letting
+    /// [`Self::find_test`] binary-search rather than scan. Empty for files written before
+    /// `TA_VERSION` 4, in which case [`Self::find_test`] falls back to a linear scan.
+    pub(crate) sorted_index: &'data [u32],
     pub(crate) timestamp: u32,
 
-    pub(crate) total_pass_count: &'data [u16],
-    pub(crate) total_fail_count: &'data [u16],
-    pub(crate) total_skip_count: &'data [u16],
-    pub(crate) total_flaky_fail_count: &'data [u16],
-    pub(crate) total_duration: &'data [f32],
+    pub(crate) total_pass_count: Cow<'data, [u16]>,
+    pub(crate) total_fail_count: Cow<'data, [u16]>,
+    pub(crate) total_skip_count: Cow<'data, [u16]>,
+    pub(crate) total_flaky_fail_count: Cow<'data, [u16]>,
+    pub(crate) total_duration: Cow<'data, [f32]>,
+
+    pub(crate) last_timestamp: Cow<'data, [u32]>,
+    pub(crate) last_duration: Cow<'data, [f32]>,
+
+    /// The per-test, per-day, per-bucket duration histogram. Empty for files written before
+    /// `TA_VERSION` 2, in which case every bucket reads as zero.
+    pub(crate) duration_histogram: Cow<'data, [u16]>,
+
+    /// Borrowed directly out of `buf` when the file is uncompressed, so callers reading from
+    /// an mmap get zero-copy access; owned when [`raw::Header::compression`] decompressed it.
+    pub(crate) string_bytes: Cow<'data, [u8]>,
+}
+
+/// Reads one `Pod`-typed data section out of `rest`, decompressing it first if `compression`
+/// says it isn't stored raw. Returns the section and whatever of `rest` follows it (still
+/// 8-byte-unaligned; the caller is responsible for calling [`align_to`] before the next
+/// section).
+fn read_section<'data, T: Pod + Clone>(
+    rest: &'data [u8],
+    compression: raw::CompressionType,
+    count: usize,
+) -> Result<(Cow<'data, [T]>, &'data [u8]), TestAnalyticsErrorKind> {
+    match compression {
+        raw::CompressionType::None => {
+            let (slice, rest) =
+                T::slice_from_prefix(rest, count).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+            Ok((Cow::Borrowed(slice), rest))
+        }
+        raw::CompressionType::Lz4 | raw::CompressionType::Zstd => {
+            let (section_header, rest) = raw::SectionHeader::ref_from_prefix(rest)
+                .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+            let compressed_len = section_header.compressed_len as usize;
+            let uncompressed_len = section_header.uncompressed_len as usize;
+
+            let compressed = rest
+                .get(..compressed_len)
+                .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+            let rest = &rest[compressed_len..];
+
+            let decompressed = match compression {
+                raw::CompressionType::Lz4 => {
+                    lz4_flex::block::decompress(compressed, uncompressed_len)
+                        .map_err(|_| TestAnalyticsErrorKind::DecompressionFailed)?
+                }
+                raw::CompressionType::Zstd => zstd::bulk::decompress(compressed, uncompressed_len)
+                    .map_err(|_| TestAnalyticsErrorKind::DecompressionFailed)?,
+                raw::CompressionType::None => unreachable!(),
+            };
+
+            let (slice, _) = T::slice_from_prefix(&decompressed, count)
+                .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+            Ok((Cow::Owned(slice.to_vec()), rest))
+        }
+    }
+}
+
+/// Reads one of the five `u16` count columns out of `rest`, honoring `encoding`.
+///
+/// [`raw::ColumnEncoding::Fixed`] delegates straight to [`read_section`], so `compression`
+/// still applies. [`raw::ColumnEncoding::Varint`] columns are always stored uncompressed (see
+/// [`raw::ColumnEncoding`]), so `compression` is ignored for them.
+fn read_count_section<'data>(
+    rest: &'data [u8],
+    encoding: raw::ColumnEncoding,
+    compression: raw::CompressionType,
+    count: usize,
+) -> Result<(Cow<'data, [u16]>, &'data [u8]), TestAnalyticsErrorKind> {
+    match encoding {
+        raw::ColumnEncoding::Fixed => read_section(rest, compression, count),
+        raw::ColumnEncoding::Varint => {
+            let (values, rest) = read_varint_column(rest, count)?;
+            Ok((Cow::Owned(values), rest))
+        }
+    }
+}
 
-    pub(crate) last_timestamp: &'data [u32],
-    pub(crate) last_duration: &'data [f32],
+/// Reads a length-prefixed unsigned-LEB128-varint-encoded column of `count` `u16`s out of
+/// `rest`: a `u32` byte length, followed by that many bytes of varint data.
+fn read_varint_column(
+    rest: &[u8],
+    count: usize,
+) -> Result<(Vec<u16>, &[u8]), TestAnalyticsErrorKind> {
+    let (len, rest) = u32::ref_from_prefix(rest).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+    let len = *len as usize;
 
-    pub(crate) string_bytes: &'data [u8],
+    let encoded = rest
+        .get(..len)
+        .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+    let rest = &rest[len..];
+
+    let mut reader = VarintColumnReader::new(encoded);
+    let mut values = Vec::with_capacity(count);
+    for _ in 0..count {
+        values.push(reader.next().ok_or(TestAnalyticsErrorKind::InvalidTables)?);
+    }
+
+    Ok((values, rest))
+}
+
+/// Incrementally decodes a stream of unsigned LEB128-encoded `u16`s, the way neqo-common's
+/// `IncrementalDecoder` walks a byte buffer: each value is one or more 7-bit groups, low byte
+/// first, with the high bit of each byte set on every group but the last.
+struct VarintColumnReader<'data> {
+    bytes: &'data [u8],
+    pos: usize,
+}
+
+impl<'data> VarintColumnReader<'data> {
+    fn new(bytes: &'data [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Reads the next value off the stream. Returns `None`, rather than panicking, if the
+    /// stream ends mid-value or a value's continuation bits would overflow a `u16`.
+    fn next(&mut self) -> Option<u16> {
+        let mut value: u32 = 0;
+        let mut shift = 0u32;
+        loop {
+            let byte = *self.bytes.get(self.pos)?;
+            self.pos += 1;
+            value |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift >= 16 {
+                return None;
+            }
+        }
+        u16::try_from(value).ok()
+    }
+}
+
+/// Reads the UTF-8 name of `test` out of `string_bytes` — the canonical sort key
+/// [`TestAnalytics::sorted_index`] orders by, that
+/// [`TestAnalyticsWriter`](super::writer::TestAnalyticsWriter) sorts by when building that
+/// index, and that [`TestAnalytics::find_test`]/[`TestAnalytics::get`] bisect on.
+pub(crate) fn test_sort_key<'a>(test: &raw::Test, string_bytes: &'a [u8]) -> &'a str {
+    watto::StringTable::read(string_bytes, test.name_offset as usize).unwrap_or_default()
 }
 
 impl<'data> TestAnalytics<'data> {
     /// Parses the given buffer into [`TestAnalytics`].
     pub fn parse(buf: &'data [u8], timestamp: u32) -> Result<Self, TestAnalyticsError> {
-        let (header, rest) =
-            raw::Header::ref_from_prefix(buf).ok_or(TestAnalyticsErrorKind::InvalidHeader)?;
+        // `version: 1` files are 4 bytes shorter, predating `num_buckets`. Peek at the
+        // `version: 1`-shaped header first to learn which layout to commit to.
+        let (v1_header, v1_rest) =
+            raw::HeaderV1::ref_from_prefix(buf).ok_or(TestAnalyticsErrorKind::InvalidHeader)?;
 
-        if header.magic != raw::TA_MAGIC {
-            return Err(TestAnalyticsErrorKind::InvalidMagic(header.magic).into());
+        if v1_header.magic != raw::TA_MAGIC {
+            return Err(TestAnalyticsErrorKind::InvalidMagic(v1_header.magic).into());
         }
 
-        if header.version != TA_VERSION {
-            return Err(TestAnalyticsErrorKind::WrongVersion(header.version).into());
-        }
+        let (header, rest) = if v1_header.version == 1 {
+            let header = raw::Header {
+                magic: v1_header.magic,
+                version: v1_header.version,
+                timestamp: v1_header.timestamp,
+                num_tests: v1_header.num_tests,
+                num_days: v1_header.num_days,
+                flags_set_len: v1_header.flags_set_len,
+                string_bytes: v1_header.string_bytes,
+                num_buckets: 0,
+                compression: raw::CompressionType::None as u32,
+                encoding: raw::ColumnEncoding::Fixed as u32,
+                checksum: 0,
+            };
+            (header, v1_rest)
+        } else if v1_header.version == 2 {
+            let (v2_header, rest) =
+                raw::HeaderV2::ref_from_prefix(buf).ok_or(TestAnalyticsErrorKind::InvalidHeader)?;
+            let header = raw::Header {
+                magic: v2_header.magic,
+                version: v2_header.version,
+                timestamp: v2_header.timestamp,
+                num_tests: v2_header.num_tests,
+                num_days: v2_header.num_days,
+                flags_set_len: v2_header.flags_set_len,
+                string_bytes: v2_header.string_bytes,
+                num_buckets: v2_header.num_buckets,
+                compression: raw::CompressionType::None as u32,
+                encoding: raw::ColumnEncoding::Fixed as u32,
+                checksum: 0,
+            };
+            (header, rest)
+        } else if v1_header.version == 3 || v1_header.version == 4 {
+            // `version: 3`/`version: 4` share the same header layout, predating varint column
+            // encoding; `version: 3` additionally lacks the sorted name index, handled below.
+            let (v3_header, rest) =
+                raw::HeaderV3::ref_from_prefix(buf).ok_or(TestAnalyticsErrorKind::InvalidHeader)?;
+            let header = raw::Header {
+                magic: v3_header.magic,
+                version: v3_header.version,
+                timestamp: v3_header.timestamp,
+                num_tests: v3_header.num_tests,
+                num_days: v3_header.num_days,
+                flags_set_len: v3_header.flags_set_len,
+                string_bytes: v3_header.string_bytes,
+                num_buckets: v3_header.num_buckets,
+                compression: v3_header.compression,
+                encoding: raw::ColumnEncoding::Fixed as u32,
+                checksum: 0,
+            };
+            (header, rest)
+        } else if v1_header.version == 5 {
+            // `version: 5` predates the post-header checksum; read as `checksum: 0`, which
+            // `parse_verified` treats as "unchecked".
+            let (v4_header, rest) =
+                raw::HeaderV4::ref_from_prefix(buf).ok_or(TestAnalyticsErrorKind::InvalidHeader)?;
+            let header = raw::Header {
+                magic: v4_header.magic,
+                version: v4_header.version,
+                timestamp: v4_header.timestamp,
+                num_tests: v4_header.num_tests,
+                num_days: v4_header.num_days,
+                flags_set_len: v4_header.flags_set_len,
+                string_bytes: v4_header.string_bytes,
+                num_buckets: v4_header.num_buckets,
+                compression: v4_header.compression,
+                encoding: v4_header.encoding,
+                checksum: 0,
+            };
+            (header, rest)
+        } else if v1_header.version == TA_VERSION {
+            let (header, rest) =
+                raw::Header::ref_from_prefix(buf).ok_or(TestAnalyticsErrorKind::InvalidHeader)?;
+            (header.clone(), rest)
+        } else {
+            return Err(TestAnalyticsErrorKind::WrongVersion(v1_header.version).into());
+        };
+
+        let compression = raw::CompressionType::from_u32(header.compression)
+            .ok_or(TestAnalyticsErrorKind::InvalidHeader)?;
+        let encoding = raw::ColumnEncoding::from_u32(header.encoding)
+            .ok_or(TestAnalyticsErrorKind::InvalidHeader)?;
 
         let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
         let (tests, rest) = raw::Test::slice_from_prefix(rest, header.num_tests as usize)
             .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
 
+        let (sorted_index, rest) = if header.version >= 4 {
+            let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+            let (sorted_index, rest) = u32::slice_from_prefix(rest, header.num_tests as usize)
+                .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+            (sorted_index, rest)
+        } else {
+            (&[][..], rest)
+        };
+
         let expected_data = header.num_tests as usize * header.num_days as usize;
 
         let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
-        let (total_pass_count, rest) = u16::slice_from_prefix(rest, expected_data)
-            .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+        let (total_pass_count, rest) =
+            read_count_section(rest, encoding, compression, expected_data)?;
 
         let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
-        let (total_fail_count, rest) = u16::slice_from_prefix(rest, expected_data)
-            .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+        let (total_fail_count, rest) =
+            read_count_section(rest, encoding, compression, expected_data)?;
 
         let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
-        let (total_skip_count, rest) = u16::slice_from_prefix(rest, expected_data)
-            .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+        let (total_skip_count, rest) =
+            read_count_section(rest, encoding, compression, expected_data)?;
 
         let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
-        let (total_flaky_fail_count, rest) = u16::slice_from_prefix(rest, expected_data)
-            .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+        let (total_flaky_fail_count, rest) =
+            read_count_section(rest, encoding, compression, expected_data)?;
 
         let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
-        let (total_duration, rest) = f32::slice_from_prefix(rest, expected_data)
-            .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+        let (total_duration, rest) = read_section(rest, compression, expected_data)?;
 
         let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
-        let (last_timestamp, rest) = u32::slice_from_prefix(rest, expected_data)
-            .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+        let (last_timestamp, rest) = read_section(rest, compression, expected_data)?;
 
         let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
-        let (last_duration, rest) = f32::slice_from_prefix(rest, expected_data)
-            .ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+        let (last_duration, rest) = read_section(rest, compression, expected_data)?;
+
+        let expected_buckets = expected_data * header.num_buckets as usize;
+        let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::InvalidTables)?;
+        let (duration_histogram, rest) =
+            read_count_section(rest, encoding, compression, expected_buckets)?;
 
         let (_, rest) = align_to(rest, 8).ok_or(TestAnalyticsErrorKind::UnexpectedStringBytes {
             expected: header.string_bytes as usize,
             found: 0,
         })?;
-        let string_bytes = rest.get(..header.string_bytes as usize).ok_or(
-            TestAnalyticsErrorKind::UnexpectedStringBytes {
-                expected: header.string_bytes as usize,
-                found: rest.len(),
-            },
-        )?;
+        let (string_bytes, _rest) = read_section(rest, compression, header.string_bytes as usize)
+            .map_err(|_| TestAnalyticsErrorKind::UnexpectedStringBytes {
+            expected: header.string_bytes as usize,
+            found: rest.len(),
+        })?;
 
         Ok(Self {
             header,
             tests,
+            sorted_index,
             timestamp: timestamp.max(header.timestamp),
 
             total_pass_count,
@@ -102,28 +343,412 @@ impl<'data> TestAnalytics<'data> {
 
             last_timestamp,
             last_duration,
+            duration_histogram,
 
             string_bytes,
         })
     }
 
+    /// Parses `buf` like [`Self::parse`], additionally recomputing [`raw::Header::checksum`]
+    /// over the bytes following the header and failing with
+    /// [`TestAnalyticsErrorKind::ChecksumMismatch`] if it disagrees, to catch corruption in
+    /// files that are read back (and potentially mmap'd) after being written, merged, or
+    /// garbage-collected in place.
+    ///
+    /// A `checksum` of `0` is treated as "unchecked" rather than verified, since files written
+    /// before `TA_VERSION` 6 don't have one. Callers that trust their storage can use the
+    /// cheaper [`Self::parse`] instead.
+    pub fn parse_verified(buf: &'data [u8], timestamp: u32) -> Result<Self, TestAnalyticsError> {
+        let parsed = Self::parse(buf, timestamp)?;
+
+        if parsed.header.checksum != 0 {
+            let header_len = mem::size_of::<raw::Header>();
+            let rest = buf
+                .get(header_len..)
+                .ok_or(TestAnalyticsErrorKind::InvalidHeader)?;
+
+            if crc32fast::hash(rest) != parsed.header.checksum {
+                return Err(TestAnalyticsErrorKind::ChecksumMismatch.into());
+            }
+        }
+
+        Ok(parsed)
+    }
+
     /// Iterates over the [`Test`]s included in the [`TestAnalytics`] summary.
     pub fn tests(&self) -> impl Iterator<Item = Test<'data, '_>> + '_ {
-        let num_days = self.header.num_days as usize;
-        self.tests.iter().enumerate().map(move |(i, test)| {
-            let start_idx = i * num_days;
-            let latest_test_timestamp = self.last_timestamp[start_idx];
-            let today_offset = offset_from_today(latest_test_timestamp, self.timestamp);
-
-            let data_range = start_idx..start_idx + num_days;
-            Test {
-                today_offset,
-                container: self,
-                data: test,
-                data_range,
+        (0..self.tests.len()).map(move |i| self.test_at(i))
+    }
+
+    /// Iterates over [`Self::tests`], keeping only those matching `filter` over the given day
+    /// `range`.
+    ///
+    /// [`FlakeFilter::FlakyOnly`] skips tests whose summed `total_flaky_fail_count` over `range`
+    /// is zero, i.e. tests that have never flipped outcome (failed, then passed on retry)
+    /// within the same run; see [`TestAnalyticsWriter::add_test_run_grouped`] for how that
+    /// count is populated.
+    pub fn tests_filtered(
+        &self,
+        range: Range<usize>,
+        filter: FlakeFilter,
+    ) -> impl Iterator<Item = Test<'data, '_>> + '_ {
+        self.tests().filter(move |test| match filter {
+            FlakeFilter::Any => true,
+            FlakeFilter::FlakyOnly => {
+                test.get_aggregates(range.clone(), None)
+                    .total_flaky_fail_count
+                    > 0
             }
         })
     }
+
+    /// Builds the [`Test`] view for the `i`th entry of `self.tests`.
+    fn test_at(&self, i: usize) -> Test<'data, '_> {
+        let num_days = self.header.num_days as usize;
+        let start_idx = i * num_days;
+        let latest_test_timestamp = self.last_timestamp[start_idx];
+        let today_offset = offset_from_today(latest_test_timestamp, self.timestamp);
+
+        let data_range = start_idx..start_idx + num_days;
+        Test {
+            today_offset,
+            container: self,
+            data: &self.tests[i],
+            data_range,
+        }
+    }
+
+    /// Looks up a single test by name, without constructing a [`Test`] for every other entry.
+    ///
+    /// Binary-searches [`Self::sorted_index`] when it's present (`TA_VERSION` >= 4), falling
+    /// back to a linear scan for older files that were written without it.
+    pub fn find_test(&self, name: &str) -> Option<Test<'data, '_>> {
+        if self.sorted_index.is_empty() {
+            let i = self
+                .tests
+                .iter()
+                .position(|test| test_sort_key(test, self.string_bytes.as_ref()) == name)?;
+            return Some(self.test_at(i));
+        }
+
+        let found = self
+            .sorted_index
+            .binary_search_by(|&i| {
+                test_sort_key(&self.tests[i as usize], self.string_bytes.as_ref()).cmp(name)
+            })
+            .ok()?;
+
+        Some(self.test_at(self.sorted_index[found] as usize))
+    }
+
+    /// Looks up the single test matching `name`, `testsuite`, and `flag` exactly, without
+    /// constructing a [`Test`] for every other entry, returning `None` if no such test has any
+    /// recorded data within `range`.
+    ///
+    /// Bisects [`Self::sorted_index`] for `name` the same way [`Self::find_test`] does, then
+    /// scans the (typically tiny) run of same-named entries the bisect lands in for the
+    /// `testsuite`/`flag` combination, since the index is only sorted by name — see
+    /// [`test_sort_key`]. `classname` is accepted for symmetry with
+    /// [`Testrun`](crate::testrun::Testrun), which records one, but is otherwise unused: the
+    /// on-disk [`raw::Test`] record has no `classname` field to match it against, the same gap
+    /// [`TestSnapshot`]'s doc comment calls out.
+    pub fn get(
+        &self,
+        name: &str,
+        _classname: &str,
+        testsuite: &str,
+        flag: Option<&str>,
+        range: Range<usize>,
+    ) -> Option<Test<'data, '_>> {
+        for i in self.matching_name_indices(name) {
+            let test = self.test_at(i);
+            if test.testsuite().ok()? != testsuite || test.flag().ok()? != flag {
+                continue;
+            }
+
+            let aggregates = test.get_aggregates(range.clone(), None);
+            let has_data = aggregates.total_pass_count
+                + aggregates.total_fail_count
+                + aggregates.total_skip_count
+                > 0;
+            if has_data {
+                return Some(test);
+            }
+        }
+
+        None
+    }
+
+    /// Returns the indices into `self.tests` of every entry named `name`, for [`Self::get`].
+    ///
+    /// Bisects [`Self::sorted_index`] (falling back to a linear scan for pre-`TA_VERSION`-4
+    /// files without one), then walks outward from the match in both directions, since more
+    /// than one test can share a name across different testsuites/flags.
+    fn matching_name_indices(&self, name: &str) -> Vec<usize> {
+        if self.sorted_index.is_empty() {
+            return self
+                .tests
+                .iter()
+                .enumerate()
+                .filter(|(_, test)| test_sort_key(test, self.string_bytes.as_ref()) == name)
+                .map(|(i, _)| i)
+                .collect();
+        }
+
+        let Ok(found) = self.sorted_index.binary_search_by(|&i| {
+            test_sort_key(&self.tests[i as usize], self.string_bytes.as_ref()).cmp(name)
+        }) else {
+            return vec![];
+        };
+
+        let key_at = |idx: usize| {
+            test_sort_key(
+                &self.tests[self.sorted_index[idx] as usize],
+                self.string_bytes.as_ref(),
+            )
+        };
+
+        let mut lo = found;
+        while lo > 0 && key_at(lo - 1) == name {
+            lo -= 1;
+        }
+        let mut hi = found;
+        while hi + 1 < self.sorted_index.len() && key_at(hi + 1) == name {
+            hi += 1;
+        }
+
+        (lo..=hi)
+            .map(|idx| self.sorted_index[idx] as usize)
+            .collect()
+    }
+
+    /// Returns the `k` [`Test`]s with the highest `metric` over the given day `range`.
+    ///
+    /// Tests are returned in descending order of `metric`. This streams over [`Self::tests`]
+    /// while keeping a bounded min-heap of size `k`, so peak memory is `O(k)` rather than
+    /// `O(num_tests)`.
+    pub fn top_tests(
+        &self,
+        metric: RankMetric,
+        range: Range<usize>,
+        k: usize,
+    ) -> Vec<(Test<'data, '_>, Aggregates)> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let mut heap: BinaryHeap<Reverse<RankedTest<'data, '_>>> = BinaryHeap::with_capacity(k);
+
+        for test in self.tests() {
+            let aggregates = test.get_aggregates(range.clone(), None);
+            let score = metric.score(&aggregates);
+            let ranked = RankedTest {
+                score,
+                test,
+                aggregates,
+            };
+
+            if heap.len() < k {
+                heap.push(Reverse(ranked));
+            } else if heap
+                .peek()
+                .is_some_and(|Reverse(lowest)| ranked.score > lowest.score)
+            {
+                heap.pop();
+                heap.push(Reverse(ranked));
+            }
+        }
+
+        let mut top: Vec<_> = heap
+            .into_iter()
+            .map(|Reverse(ranked)| (ranked.test, ranked.aggregates))
+            .collect();
+        top.sort_by(|(_, a), (_, b)| metric.score(b).total_cmp(&metric.score(a)));
+        top
+    }
+
+    /// Writes `range`'s aggregates as OpenMetrics/Prometheus text exposition format to `writer`:
+    /// one series per test, labeled with `name`, `testsuite`, and `flag`, for the
+    /// `test_pass_total`/`test_fail_total`/`test_skip_total` counters and the
+    /// `test_avg_duration_seconds` gauge.
+    ///
+    /// This is read-only over the already-parsed structure and doesn't touch the on-disk
+    /// format.
+    pub fn write_openmetrics(
+        &self,
+        range: Range<usize>,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let rows: Vec<_> = self
+            .tests()
+            .map(|test| {
+                let aggregates = test.get_aggregates(range.clone(), None);
+                (test, aggregates)
+            })
+            .collect();
+
+        writeln!(writer, "# TYPE test_pass_total counter")?;
+        writeln!(
+            writer,
+            "# HELP test_pass_total Total number of passing test runs."
+        )?;
+        for (test, aggregates) in &rows {
+            writeln!(
+                writer,
+                "test_pass_total{{{}}} {}",
+                openmetrics_labels(test),
+                aggregates.total_pass_count
+            )?;
+        }
+
+        writeln!(writer, "# TYPE test_fail_total counter")?;
+        writeln!(
+            writer,
+            "# HELP test_fail_total Total number of failing test runs."
+        )?;
+        for (test, aggregates) in &rows {
+            writeln!(
+                writer,
+                "test_fail_total{{{}}} {}",
+                openmetrics_labels(test),
+                aggregates.total_fail_count
+            )?;
+        }
+
+        writeln!(writer, "# TYPE test_skip_total counter")?;
+        writeln!(
+            writer,
+            "# HELP test_skip_total Total number of skipped test runs."
+        )?;
+        for (test, aggregates) in &rows {
+            writeln!(
+                writer,
+                "test_skip_total{{{}}} {}",
+                openmetrics_labels(test),
+                aggregates.total_skip_count
+            )?;
+        }
+
+        writeln!(writer, "# TYPE test_avg_duration_seconds gauge")?;
+        writeln!(
+            writer,
+            "# HELP test_avg_duration_seconds Average test duration in seconds."
+        )?;
+        for (test, aggregates) in &rows {
+            writeln!(
+                writer,
+                "test_avg_duration_seconds{{{}}} {}",
+                openmetrics_labels(test),
+                aggregates.avg_duration
+            )?;
+        }
+
+        writeln!(writer, "# EOF")?;
+        Ok(())
+    }
+
+    /// Builds a JSON snapshot of every test's per-day aggregates over `range`, for debugging,
+    /// cross-version migration, or feeding into other tooling.
+    ///
+    /// The binary format stays authoritative: this is a derived view, and
+    /// [`TestAnalyticsWriter::from_json_value`] reconstructs a writer from it by replaying each
+    /// day through the normal [`TestAnalyticsWriter::add_test_run`] path rather than copying raw
+    /// bytes, so the sorted test table and bucket layout are re-established rather than trusted
+    /// from the JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json_value(
+        &self,
+        range: Range<usize>,
+    ) -> Result<serde_json::Value, TestAnalyticsError> {
+        let snapshots = self
+            .tests()
+            .map(|test| test.to_snapshot(range.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        serde_json::to_value(&snapshots).map_err(|_| TestAnalyticsErrorKind::InvalidTables.into())
+    }
+}
+
+/// Builds the `name="...",testsuite="...",flag="..."` label set [`TestAnalytics::write_openmetrics`]
+/// attaches to each series, escaping label values per the OpenMetrics text format.
+///
+/// Falls back to an empty string for a test whose name/testsuite/flag can't be read out of the
+/// string table, rather than aborting the whole scrape over one bad entry.
+fn openmetrics_labels(test: &Test) -> String {
+    let name = test.name().unwrap_or_default();
+    let testsuite = test.testsuite().unwrap_or_default();
+    let flag = test.flag().ok().flatten().unwrap_or_default();
+
+    format!(
+        r#"name="{}",testsuite="{}",flag="{}""#,
+        escape_label_value(name),
+        escape_label_value(testsuite),
+        escape_label_value(flag)
+    )
+}
+
+/// Escapes `\`, `"`, and newlines in a label value, per the OpenMetrics text format.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Which of [`TestAnalytics::tests_filtered`]'s tests get returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlakeFilter {
+    /// Every test, regardless of flakiness.
+    Any,
+    /// Only tests with at least one flaky outcome over the requested day range.
+    FlakyOnly,
+}
+
+/// The metric [`TestAnalytics::top_tests`] ranks tests by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankMetric {
+    FailureRate,
+    FlakeRate,
+    AvgDuration,
+    FailCount,
+}
+
+impl RankMetric {
+    fn score(self, aggregates: &Aggregates) -> f32 {
+        match self {
+            RankMetric::FailureRate => aggregates.failure_rate,
+            RankMetric::FlakeRate => aggregates.flake_rate,
+            RankMetric::AvgDuration => aggregates.avg_duration as f32,
+            RankMetric::FailCount => aggregates.total_fail_count as f32,
+        }
+    }
+}
+
+/// A [`Test`] paired with its ranking score, ordered by that score for use in a [`BinaryHeap`].
+struct RankedTest<'data, 'parsed> {
+    score: f32,
+    test: Test<'data, 'parsed>,
+    aggregates: Aggregates,
+}
+
+impl PartialEq for RankedTest<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.score == other.score
+    }
+}
+
+impl Eq for RankedTest<'_, '_> {}
+
+impl PartialOrd for RankedTest<'_, '_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RankedTest<'_, '_> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.score.total_cmp(&other.score)
+    }
 }
 
 impl<'data> fmt::Debug for TestAnalytics<'data> {
@@ -137,6 +762,32 @@ impl<'data> fmt::Debug for TestAnalytics<'data> {
     }
 }
 
+/// One day's aggregated counts for a single test, as produced by [`Test::to_snapshot`] and
+/// consumed by [`TestAnalyticsWriter`](super::writer::TestAnalyticsWriter)'s `from_json_value`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DaySnapshot {
+    pub pass_count: u32,
+    pub fail_count: u32,
+    pub skip_count: u32,
+    pub avg_duration: f64,
+}
+
+/// An owned, JSON-serializable snapshot of a single test, as returned within
+/// [`TestAnalytics::to_json_value`]'s array. `rows` holds one [`DaySnapshot`] per day of the
+/// requested range, ordered from the most recent day to the oldest.
+///
+/// Note there's no `classname` here: the binary format itself doesn't carry one, only `name`,
+/// `testsuite`, and an optional `flag`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestSnapshot {
+    pub name: String,
+    pub testsuite: String,
+    pub flag: Option<String>,
+    pub rows: Vec<DaySnapshot>,
+}
+
 /// This represents a specific test for which test analytics data is gathered.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Test<'data, 'parsed> {
@@ -149,13 +800,92 @@ pub struct Test<'data, 'parsed> {
 
 impl<'data, 'parsed> Test<'data, 'parsed> {
     /// Returns the name of the test.
-    pub fn name(&self) -> Result<&'data str, TestAnalyticsError> {
-        watto::StringTable::read(self.container.string_bytes, self.data.name_offset as usize)
-            .map_err(|_| TestAnalyticsErrorKind::InvalidStringReference.into())
+    ///
+    /// Borrowed from `self` rather than `'data`: when the string table was compressed on disk,
+    /// it was decompressed into an owned buffer that only lives as long as the parsed
+    /// [`TestAnalytics`], not the original file buffer.
+    pub fn name(&self) -> Result<&'parsed str, TestAnalyticsError> {
+        watto::StringTable::read(
+            self.container.string_bytes.as_ref(),
+            self.data.name_offset as usize,
+        )
+        .map_err(|_| TestAnalyticsErrorKind::InvalidStringReference.into())
+    }
+
+    /// Returns the name of the testsuite this test belongs to.
+    pub fn testsuite(&self) -> Result<&'parsed str, TestAnalyticsError> {
+        watto::StringTable::read(
+            self.container.string_bytes.as_ref(),
+            self.data.testsuite_offset as usize,
+        )
+        .map_err(|_| TestAnalyticsErrorKind::InvalidStringReference.into())
+    }
+
+    /// Returns the flag this test's data was recorded under, if any.
+    ///
+    /// An empty string, which is the offset stored for a test that was not
+    /// recorded with a flag, is treated as "no flag".
+    pub fn flag(&self) -> Result<Option<&'parsed str>, TestAnalyticsError> {
+        let flag = watto::StringTable::read(
+            self.container.string_bytes.as_ref(),
+            self.data.flag_set_offset as usize,
+        )
+        .map_err(|_| TestAnalyticsErrorKind::InvalidStringReference)?;
+
+        Ok(if flag.is_empty() { None } else { Some(flag) })
+    }
+
+    /// Builds an owned, JSON-serializable [`TestSnapshot`] of this test's per-day counts over
+    /// `range`, for [`TestAnalytics::to_json_value`]. Unlike [`Self::get_aggregates`], which
+    /// sums the range into one set of rates, this keeps one [`DaySnapshot`] per day, ordered
+    /// from the most recent day (index `0`) to the oldest.
+    #[cfg(feature = "serde")]
+    pub fn to_snapshot(&self, range: Range<usize>) -> Result<TestSnapshot, TestAnalyticsError> {
+        let adjusted_range =
+            adjust_selection_range(self.data_range.clone(), range, self.today_offset);
+
+        let rows = adjusted_range
+            .map(|idx| {
+                let pass_count = self.container.total_pass_count[idx] as u32;
+                let fail_count = self.container.total_fail_count[idx] as u32;
+                let skip_count = self.container.total_skip_count[idx] as u32;
+                let total_run_count = pass_count + fail_count;
+                let avg_duration = if total_run_count > 0 {
+                    self.container.total_duration[idx] as f64 / total_run_count as f64
+                } else {
+                    0.0
+                };
+
+                DaySnapshot {
+                    pass_count,
+                    fail_count,
+                    skip_count,
+                    avg_duration,
+                }
+            })
+            .collect();
+
+        Ok(TestSnapshot {
+            name: self.name()?.to_string(),
+            testsuite: self.testsuite()?.to_string(),
+            flag: self.flag()?.map(str::to_string),
+            rows,
+        })
     }
 
     /// Calculates aggregate data for the given [`Range`] of days.
-    pub fn get_aggregates(&self, desired_range: Range<usize>) -> Aggregates {
+    ///
+    /// If `half_life_days` is given, this additionally computes
+    /// [`Aggregates::weighted_failure_rate`] and [`Aggregates::weighted_flake_rate`], which
+    /// exponentially decay the contribution of each day the further back it lies within
+    /// `desired_range`, halving every `half_life_days` days. This biases the rate towards
+    /// recent data, so a test that just started flaking shows up faster than in the plain,
+    /// uniformly-weighted `failure_rate`/`flake_rate`.
+    pub fn get_aggregates(
+        &self,
+        desired_range: Range<usize>,
+        half_life_days: Option<f64>,
+    ) -> Aggregates {
         let adjusted_range =
             adjust_selection_range(self.data_range.clone(), desired_range, self.today_offset);
 
@@ -191,6 +921,15 @@ impl<'data, 'parsed> Test<'data, 'parsed> {
             (0., 0., 0.)
         };
 
+        let (weighted_failure_rate, weighted_flake_rate) = match half_life_days {
+            Some(half_life_days) => {
+                self.weighted_failure_and_flake_rate(adjusted_range.clone(), half_life_days)
+            }
+            None => (None, None),
+        };
+
+        let duration_histogram = self.duration_histogram(adjusted_range);
+
         Aggregates {
             total_pass_count,
             total_fail_count,
@@ -199,8 +938,71 @@ impl<'data, 'parsed> Test<'data, 'parsed> {
 
             failure_rate,
             flake_rate,
+            weighted_failure_rate,
+            weighted_flake_rate,
 
             avg_duration,
+            duration_histogram,
+        }
+    }
+
+    /// Sums the per-bucket duration histogram across `adjusted_range`.
+    ///
+    /// Returns an empty `Vec` if the file predates the histogram (`TA_VERSION` 1).
+    fn duration_histogram(&self, adjusted_range: Range<usize>) -> Vec<u32> {
+        let num_buckets = self.container.header.num_buckets as usize;
+        let mut histogram = vec![0u32; num_buckets];
+        if num_buckets == 0 {
+            return histogram;
+        }
+
+        for idx in adjusted_range {
+            let start = idx * num_buckets;
+            let buckets = &self.container.duration_histogram[start..start + num_buckets];
+            for (total, count) in histogram.iter_mut().zip(buckets) {
+                *total += *count as u32;
+            }
+        }
+
+        histogram
+    }
+
+    /// Computes the exponentially time-decayed failure/flake rate over `adjusted_range`.
+    ///
+    /// The most recent day in the range is weighted `1.0`, and each day further back is
+    /// weighted `0.5^(age_in_days / half_life_days)`. Returns `0.0` for both rates if the
+    /// weighted denominator is zero, matching the plain rate's zero-run-count behavior.
+    fn weighted_failure_and_flake_rate(
+        &self,
+        adjusted_range: Range<usize>,
+        half_life_days: f64,
+    ) -> (Option<f32>, Option<f32>) {
+        let decay_per_day = 0.5_f64.powf(1.0 / half_life_days);
+
+        let mut weighted_fail_count = 0.0;
+        let mut weighted_flaky_fail_count = 0.0;
+        let mut weighted_run_count = 0.0;
+
+        for (day, idx) in adjusted_range.enumerate() {
+            // Index 0 of the adjusted range is the most recent day.
+            let weight = decay_per_day.powf(day as f64);
+
+            let pass_count = self.container.total_pass_count[idx] as f64;
+            let fail_count = self.container.total_fail_count[idx] as f64;
+            let flaky_fail_count = self.container.total_flaky_fail_count[idx] as f64;
+
+            weighted_fail_count += weight * fail_count;
+            weighted_flaky_fail_count += weight * flaky_fail_count;
+            weighted_run_count += weight * (pass_count + fail_count);
+        }
+
+        if weighted_run_count > 0.0 {
+            (
+                Some((weighted_fail_count / weighted_run_count) as f32),
+                Some((weighted_flaky_fail_count / weighted_run_count) as f32),
+            )
+        } else {
+            (Some(0.0), Some(0.0))
         }
     }
 }
@@ -215,6 +1017,192 @@ pub struct Aggregates {
 
     pub failure_rate: f32,
     pub flake_rate: f32,
+    /// Exponentially time-decayed failure rate, present when `half_life_days` was given to
+    /// [`Test::get_aggregates`].
+    pub weighted_failure_rate: Option<f32>,
+    /// Exponentially time-decayed flake rate, present when `half_life_days` was given to
+    /// [`Test::get_aggregates`].
+    pub weighted_flake_rate: Option<f32>,
 
     pub avg_duration: f64,
+
+    /// The summed per-bucket duration histogram over the requested day range. Empty for files
+    /// written before `TA_VERSION` 2. Use [`Aggregates::percentile_duration_ms`] rather than
+    /// reading this directly.
+    duration_histogram: Vec<u32>,
+}
+
+impl Aggregates {
+    /// Estimates the given percentile (in `0.0..=1.0`) of test duration in milliseconds,
+    /// from the per-day duration histogram.
+    ///
+    /// This walks cumulative bucket counts until the target rank is reached, then linearly
+    /// interpolates within that bucket's `[lo, hi)` bounds. Returns `None` if there is no
+    /// histogram data for this test (e.g. the file predates `TA_VERSION` 2).
+    pub fn percentile_duration_ms(&self, percentile: f64) -> Option<f64> {
+        let total: u32 = self.duration_histogram.iter().sum();
+        if total == 0 {
+            return None;
+        }
+
+        let target_rank = percentile * total as f64;
+        let mut cumulative = 0u32;
+        for (bucket, count) in self.duration_histogram.iter().enumerate() {
+            let next_cumulative = cumulative + count;
+            let is_last_bucket = bucket == self.duration_histogram.len() - 1;
+            if next_cumulative as f64 >= target_rank || is_last_bucket {
+                let (lo, hi) = bucket_bounds_ms(bucket);
+                let within_bucket = if *count > 0 {
+                    ((target_rank - cumulative as f64) / *count as f64).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                return Some(lo + within_bucket * (hi - lo));
+            }
+            cumulative = next_cumulative;
+        }
+
+        None
+    }
+
+    /// The median (p50) test duration in milliseconds. See [`Self::percentile_duration_ms`].
+    pub fn p50_duration_ms(&self) -> Option<f64> {
+        self.percentile_duration_ms(0.5)
+    }
+
+    /// The p95 test duration in milliseconds. See [`Self::percentile_duration_ms`].
+    pub fn p95_duration_ms(&self) -> Option<f64> {
+        self.percentile_duration_ms(0.95)
+    }
+
+    /// The p99 test duration in milliseconds. See [`Self::percentile_duration_ms`].
+    pub fn p99_duration_ms(&self) -> Option<f64> {
+        self.percentile_duration_ms(0.99)
+    }
+}
+
+/// Returns the `[lo, hi)` duration bounds, in milliseconds, of the given histogram bucket.
+///
+/// Bucket `0` covers zero/sub-millisecond durations; bucket `i >= 1` covers
+/// `[2^(i-1), 2^i)` milliseconds.
+fn bucket_bounds_ms(bucket: usize) -> (f64, f64) {
+    if bucket == 0 {
+        (0.0, 1.0)
+    } else {
+        (2f64.powi(bucket as i32 - 1), 2f64.powi(bucket as i32))
+    }
+}
+
+/// Returns the histogram bucket a duration of `duration_ms` milliseconds falls into, out of
+/// `num_buckets` total buckets.
+pub(crate) fn bucket_for_duration_ms(duration_ms: f64, num_buckets: usize) -> usize {
+    if num_buckets == 0 {
+        return 0;
+    }
+    if duration_ms < 1.0 {
+        return 0;
+    }
+
+    let bucket = duration_ms.log2().floor() as i64 + 1;
+    (bucket.max(1) as usize).min(num_buckets - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::writer::TestAnalyticsWriter;
+    use crate::testrun::{Outcome, PropertiesValue, Testrun};
+
+    fn sample_testrun(name: &str, testsuite: &str) -> Testrun {
+        Testrun {
+            name: name.into(),
+            classname: "some.module".into(),
+            duration: Some(1.0),
+            outcome: Outcome::Pass,
+            testsuite: testsuite.into(),
+            failure_message: None,
+            filename: None,
+            build_url: None,
+            computed_name: name.into(),
+            properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
+        }
+    }
+
+    /// [`TestAnalytics::get`]'s indexed lookup must agree with scanning [`TestAnalytics::tests`]
+    /// for the same name/testsuite, across many inserted tests.
+    #[test]
+    fn test_get_matches_linear_scan() {
+        let mut writer = TestAnalyticsWriter::new(2, 0);
+        for i in 0..500 {
+            let name = format!("test_{i}");
+            let testsuite = format!("suite_{}", i % 7);
+            writer.add_test_run(&sample_testrun(&name, &testsuite));
+        }
+
+        let mut buf = vec![];
+        writer.serialize(&mut buf).unwrap();
+        let parsed = TestAnalytics::parse(&buf, 0).unwrap();
+
+        for i in 0..500 {
+            let name = format!("test_{i}");
+            let testsuite = format!("suite_{}", i % 7);
+
+            let scanned = parsed
+                .tests()
+                .find(|test| test.name().unwrap() == name && test.testsuite().unwrap() == testsuite)
+                .expect("every inserted test should be found by scanning");
+
+            let indexed = parsed
+                .get(&name, "irrelevant.classname", &testsuite, None, 0..2)
+                .expect("every inserted test should be found by the indexed lookup");
+
+            assert_eq!(scanned.name().unwrap(), indexed.name().unwrap());
+            assert_eq!(scanned.testsuite().unwrap(), indexed.testsuite().unwrap());
+        }
+
+        assert!(parsed
+            .get("does_not_exist", "", "suite_0", None, 0..2)
+            .is_none());
+    }
+
+    /// Merging two files that each have histogram data recorded at different timestamps must
+    /// produce the same bytes regardless of which operand [`TestAnalyticsWriter::merge`] picks
+    /// as the "larger" one to merge into — proving the duration histogram is day-shifted along
+    /// with every other column rather than staying pinned to whichever index it was first
+    /// written at.
+    #[test]
+    fn test_merge_histogram_is_commutative() {
+        use timestamps::DAY;
+
+        let mut writer_a = TestAnalyticsWriter::new(3, 2 * DAY);
+        writer_a.add_test_run(&sample_testrun("test_one", "suite"));
+        let mut buf_a = vec![];
+        writer_a.serialize(&mut buf_a).unwrap();
+
+        let mut writer_b = TestAnalyticsWriter::new(3, 0);
+        writer_b.add_test_run(&sample_testrun("test_one", "suite"));
+        let mut buf_b = vec![];
+        writer_b.serialize(&mut buf_b).unwrap();
+
+        let parsed_a = TestAnalytics::parse(&buf_a, 2 * DAY).unwrap();
+        let parsed_b = TestAnalytics::parse(&buf_b, 2 * DAY).unwrap();
+
+        let mut buf_ab = vec![];
+        TestAnalyticsWriter::merge(&parsed_a, &parsed_b, 2 * DAY)
+            .unwrap()
+            .serialize(&mut buf_ab)
+            .unwrap();
+
+        let mut buf_ba = vec![];
+        TestAnalyticsWriter::merge(&parsed_b, &parsed_a, 2 * DAY)
+            .unwrap()
+            .serialize(&mut buf_ba)
+            .unwrap();
+
+        assert_eq!(buf_ab, buf_ba);
+    }
 }
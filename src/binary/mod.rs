@@ -6,7 +6,7 @@ mod timestamps;
 mod writer;
 
 pub use error::{TestAnalyticsError, TestAnalyticsErrorKind};
-pub use format::{Test, TestAnalytics};
+pub use format::{DaySnapshot, RankMetric, Test, TestAnalytics, TestSnapshot};
 pub use writer::TestAnalyticsWriter;
 
 #[cfg(test)]
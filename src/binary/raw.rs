@@ -3,6 +3,113 @@ use watto::Pod;
 /// The magic file preamble, encoded as little-endian `CCTA`.
 pub const TA_MAGIC: u32 = u32::from_le_bytes(*b"CCTA");
 
+/// The `version: 1` layout of [`Header`], kept around to read files written before the
+/// per-day duration histogram (`num_buckets`) was added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct HeaderV1 {
+    /// The file magic representing the file format and endianness.
+    pub magic: u32,
+    /// The file format version.
+    pub version: u32,
+    /// Timestamp when the file was last touched.
+    pub timestamp: u32,
+    /// Number of tests within the file.
+    pub num_tests: u32,
+    /// Number of days worth of aggregated data.
+    pub num_days: u32,
+    /// Length of the `FlagsSet` table.
+    pub flags_set_len: u32,
+    /// Length of the string table.
+    pub string_bytes: u32,
+}
+unsafe impl Pod for HeaderV1 {}
+
+/// The `version: 2` layout of [`Header`], kept around to read files written before per-column
+/// compression (`compression`) was added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct HeaderV2 {
+    /// The file magic representing the file format and endianness.
+    pub magic: u32,
+    /// The file format version.
+    pub version: u32,
+    /// Timestamp when the file was last touched.
+    pub timestamp: u32,
+    /// Number of tests within the file.
+    pub num_tests: u32,
+    /// Number of days worth of aggregated data.
+    pub num_days: u32,
+    /// Length of the `FlagsSet` table.
+    pub flags_set_len: u32,
+    /// Length of the string table.
+    pub string_bytes: u32,
+    /// Number of log2-scaled duration histogram buckets, per test per day.
+    pub num_buckets: u32,
+}
+unsafe impl Pod for HeaderV2 {}
+
+/// The `version: 3`/`version: 4` layout of [`Header`], kept around to read files written
+/// before varint column encoding (`encoding`) was added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct HeaderV3 {
+    /// The file magic representing the file format and endianness.
+    pub magic: u32,
+    /// The file format version.
+    pub version: u32,
+    /// Timestamp when the file was last touched.
+    pub timestamp: u32,
+    /// Number of tests within the file.
+    pub num_tests: u32,
+    /// Number of days worth of aggregated data.
+    pub num_days: u32,
+    /// Length of the `FlagsSet` table.
+    pub flags_set_len: u32,
+    /// Length of the string table.
+    pub string_bytes: u32,
+    /// Number of log2-scaled duration histogram buckets, per test per day.
+    pub num_buckets: u32,
+    /// Which [`CompressionType`] each data section (every column plus the string table) is
+    /// encoded with. Stored as a raw `u32` so the header stays `Pod`; use
+    /// [`CompressionType::from_u32`] to interpret it.
+    pub compression: u32,
+}
+unsafe impl Pod for HeaderV3 {}
+
+/// The `version: 5` layout of [`Header`], kept around to read files written before the
+/// post-header checksum (`checksum`) was added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct HeaderV4 {
+    /// The file magic representing the file format and endianness.
+    pub magic: u32,
+    /// The file format version.
+    pub version: u32,
+    /// Timestamp when the file was last touched.
+    pub timestamp: u32,
+    /// Number of tests within the file.
+    pub num_tests: u32,
+    /// Number of days worth of aggregated data.
+    pub num_days: u32,
+    /// Length of the `FlagsSet` table.
+    pub flags_set_len: u32,
+    /// Length of the string table.
+    pub string_bytes: u32,
+    /// Number of log2-scaled duration histogram buckets, per test per day.
+    pub num_buckets: u32,
+    /// Which [`CompressionType`] each data section (every column plus the string table) is
+    /// encoded with. Stored as a raw `u32` so the header stays `Pod`; use
+    /// [`CompressionType::from_u32`] to interpret it.
+    pub compression: u32,
+    /// Which [`ColumnEncoding`] the five `u16` count columns (`total_pass_count`,
+    /// `total_fail_count`, `total_skip_count`, `total_flaky_fail_count`, `duration_histogram`)
+    /// are stored with. Stored as a raw `u32` so the header stays `Pod`; use
+    /// [`ColumnEncoding::from_u32`] to interpret it.
+    pub encoding: u32,
+}
+unsafe impl Pod for HeaderV4 {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[repr(C)]
 pub struct Header {
@@ -20,9 +127,82 @@ pub struct Header {
     pub flags_set_len: u32,
     /// Length of the string table.
     pub string_bytes: u32,
+    /// Number of log2-scaled duration histogram buckets, per test per day.
+    pub num_buckets: u32,
+    /// Which [`CompressionType`] each data section (every column plus the string table) is
+    /// encoded with. Stored as a raw `u32` so the header stays `Pod`; use
+    /// [`CompressionType::from_u32`] to interpret it.
+    pub compression: u32,
+    /// Which [`ColumnEncoding`] the five `u16` count columns (`total_pass_count`,
+    /// `total_fail_count`, `total_skip_count`, `total_flaky_fail_count`, `duration_histogram`)
+    /// are stored with. Stored as a raw `u32` so the header stays `Pod`; use
+    /// [`ColumnEncoding::from_u32`] to interpret it.
+    pub encoding: u32,
+    /// CRC32 checksum of every byte following the header, written once the rest of the payload
+    /// is laid out. A file written before this field existed reads as `checksum: 0`, which
+    /// [`super::TestAnalytics::parse_verified`] treats as "unchecked" rather than a mismatch.
+    pub checksum: u32,
 }
 unsafe impl Pod for Header {}
 
+/// Which layout the five `u16` count columns are stored in.
+///
+/// `Varint` is worth choosing for sparse matrices, where most entries are zero or small: each
+/// value is packed as unsigned LEB128 rather than a fixed 2 bytes, at the cost of needing an
+/// incremental decode pass rather than a zero-copy borrow. It is independent of
+/// [`CompressionType`], and always stored uncompressed — a varint stream doesn't have enough
+/// byte-level redundancy left for general-purpose compression to meaningfully shrink further.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ColumnEncoding {
+    Fixed = 0,
+    Varint = 1,
+}
+
+impl ColumnEncoding {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Fixed),
+            1 => Some(Self::Varint),
+            _ => None,
+        }
+    }
+}
+
+/// Which compression, if any, each data section of the file is encoded with.
+///
+/// Sparse columns (mostly-zero counts and timestamps for recently-added tests) compress
+/// dramatically, at the cost of the reader no longer being able to borrow them directly out of
+/// an mmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CompressionType {
+    None = 0,
+    Lz4 = 1,
+    Zstd = 2,
+}
+
+impl CompressionType {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::None),
+            1 => Some(Self::Lz4),
+            2 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Precedes each compressed data section, giving the reader the lengths it needs to find the
+/// start of the next section and to allocate the decompression target.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct SectionHeader {
+    pub uncompressed_len: u32,
+    pub compressed_len: u32,
+}
+unsafe impl Pod for SectionHeader {}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[repr(C)]
 pub struct Test {
@@ -57,7 +237,19 @@ mod tests {
 
     #[test]
     fn test_sizeof() {
-        assert_eq!(mem::size_of::<Header>(), 28);
+        assert_eq!(mem::size_of::<HeaderV1>(), 28);
+        assert_eq!(mem::align_of::<HeaderV1>(), 4);
+
+        assert_eq!(mem::size_of::<HeaderV2>(), 32);
+        assert_eq!(mem::align_of::<HeaderV2>(), 4);
+
+        assert_eq!(mem::size_of::<HeaderV3>(), 36);
+        assert_eq!(mem::align_of::<HeaderV3>(), 4);
+
+        assert_eq!(mem::size_of::<HeaderV4>(), 40);
+        assert_eq!(mem::align_of::<HeaderV4>(), 4);
+
+        assert_eq!(mem::size_of::<Header>(), 44);
         assert_eq!(mem::align_of::<Header>(), 4);
 
         assert_eq!(mem::size_of::<Test>(), 8);
@@ -65,5 +257,8 @@ mod tests {
 
         assert_eq!(mem::size_of::<TestData>(), 20);
         assert_eq!(mem::align_of::<TestData>(), 4);
+
+        assert_eq!(mem::size_of::<SectionHeader>(), 8);
+        assert_eq!(mem::align_of::<SectionHeader>(), 4);
     }
 }
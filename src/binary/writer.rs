@@ -1,15 +1,31 @@
+use std::collections::HashMap;
 use std::io::Write;
 use std::mem;
-use std::ops::AddAssign;
+use std::ops::{AddAssign, Range};
 
 use indexmap::IndexSet;
-use timestamps::{adjust_selection_range, offset_from_today, shift_data};
+use timestamps::{adjust_selection_range, offset_from_today, shift_data, DAY};
 use watto::{Pod, StringTable};
 
 use crate::testrun;
 
+use super::format::{test_sort_key, NUM_DURATION_BUCKETS};
 use super::*;
 
+/// A single test's outcomes buffered by [`TestAnalyticsWriter::add_test_run_grouped`] while its
+/// run is still in progress.
+#[derive(Debug)]
+struct RunOutcome {
+    data_idx: usize,
+    fail_count: u16,
+    passed: bool,
+    /// Duration and histogram bucket of the representative `Testrun` for this test within the
+    /// run, committed once by [`TestAnalyticsWriter::finish_run`] regardless of how many times
+    /// the test was retried. Set by the first `Testrun` observed for this test in the run.
+    duration: f32,
+    duration_bucket: usize,
+}
+
 /// The [`TestAnalytics`] File Writer.
 #[derive(Debug)]
 pub struct TestAnalyticsWriter {
@@ -27,7 +43,17 @@ pub struct TestAnalyticsWriter {
     last_timestamp: Vec<u32>,
     last_duration: Vec<f32>,
 
+    /// Per-test, per-day, per-bucket duration histogram counts, `NUM_DURATION_BUCKETS` per day.
+    duration_histogram: Vec<u16>,
+
     string_table: StringTable,
+
+    /// Outcomes seen so far for the in-progress run started by [`Self::add_test_run_grouped`],
+    /// keyed by test name. Drained by [`Self::finish_run`].
+    current_run: HashMap<String, RunOutcome>,
+    /// The `run_id` the in-progress [`Self::current_run`] batch belongs to, so a caller starting
+    /// a new run without finishing the previous one is caught rather than silently mixing them.
+    current_run_id: Option<String>,
 }
 
 impl TestAnalyticsWriter {
@@ -46,8 +72,12 @@ impl TestAnalyticsWriter {
 
             last_timestamp: vec![],
             last_duration: vec![],
+            duration_histogram: vec![],
 
             string_table: Default::default(),
+
+            current_run: HashMap::new(),
+            current_run_id: None,
         }
     }
 
@@ -58,24 +88,118 @@ impl TestAnalyticsWriter {
     ) -> Result<Self, TestAnalyticsError> {
         let tests = IndexSet::from_iter(data.tests.iter().cloned());
 
-        let string_table = StringTable::from_bytes(data.string_bytes)
+        let string_table = StringTable::from_bytes(data.string_bytes.as_ref())
             .map_err(|_| TestAnalyticsErrorKind::InvalidStringReference)?;
 
+        // Only the current bucket count can be carried over as-is; older files (or files with
+        // a different bucket count) start out with an all-zero histogram instead.
+        let duration_histogram = if data.header.num_buckets as usize == NUM_DURATION_BUCKETS {
+            data.duration_histogram.to_vec()
+        } else {
+            vec![0; data.tests.len() * data.header.num_days as usize * NUM_DURATION_BUCKETS]
+        };
+
         Ok(Self {
             timestamp,
             num_days: data.header.num_days as usize,
             tests,
-            total_pass_count: data.total_pass_count.into(),
-            total_fail_count: data.total_fail_count.into(),
-            total_skip_count: data.total_skip_count.into(),
-            total_flaky_fail_count: data.total_flaky_fail_count.into(),
-            total_duration: data.total_duration.into(),
-            last_timestamp: data.last_timestamp.into(),
-            last_duration: data.last_duration.into(),
+            total_pass_count: data.total_pass_count.to_vec(),
+            total_fail_count: data.total_fail_count.to_vec(),
+            total_skip_count: data.total_skip_count.to_vec(),
+            total_flaky_fail_count: data.total_flaky_fail_count.to_vec(),
+            total_duration: data.total_duration.to_vec(),
+            last_timestamp: data.last_timestamp.to_vec(),
+            last_duration: data.last_duration.to_vec(),
+            duration_histogram,
             string_table,
+
+            current_run: HashMap::new(),
+            current_run_id: None,
         })
     }
 
+    /// Rebuilds a writer from a JSON snapshot produced by
+    /// [`TestAnalytics::to_json_value`](super::format::TestAnalytics::to_json_value).
+    ///
+    /// The snapshot isn't trusted as raw bytes: each day is replayed, oldest to newest, through
+    /// the normal [`Self::add_test_run`] path (one call per counted pass/fail/skip), the same
+    /// way real runs would have arrived, so the sorted test table and bucket layout end up
+    /// exactly as they would from live recording rather than being copied over. `timestamp` is
+    /// treated as "today" — the snapshot's row index `0` — with earlier rows replayed at
+    /// consecutively earlier days via [`Self::advance_to`], so each test's columns are
+    /// day-shifted in place rather than the whole writer being serialized and re-parsed once
+    /// per day.
+    #[cfg(feature = "serde")]
+    pub fn from_json_value(
+        value: &serde_json::Value,
+        timestamp: u32,
+    ) -> Result<Self, TestAnalyticsError> {
+        let snapshots: Vec<TestSnapshot> = serde_json::from_value(value.clone())
+            .map_err(|_| TestAnalyticsErrorKind::InvalidTables)?;
+
+        let num_days = snapshots
+            .iter()
+            .map(|test| test.rows.len())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let oldest_timestamp = timestamp.saturating_sub((num_days - 1) as u32 * DAY);
+        let mut writer = Self::new(num_days, oldest_timestamp);
+
+        for day_offset in (0..num_days).rev() {
+            let day_timestamp = timestamp.saturating_sub(day_offset as u32 * DAY);
+            writer.advance_to(day_timestamp);
+
+            for snapshot in &snapshots {
+                let Some(row) = snapshot.rows.get(day_offset) else {
+                    continue;
+                };
+
+                let mut test = testrun::Testrun {
+                    name: snapshot.name.as_str().into(),
+                    classname: "".into(),
+                    duration: Some(row.avg_duration),
+                    outcome: testrun::Outcome::Pass,
+                    testsuite: snapshot.testsuite.as_str().into(),
+                    failure_message: None,
+                    filename: None,
+                    build_url: None,
+                    computed_name: snapshot.name.as_str().into(),
+                    properties: testrun::PropertiesValue(None),
+                    system_out: None,
+                    system_err: None,
+                    reruns: vec![],
+                    timestamp: None,
+                };
+
+                for (outcome, count) in [
+                    (testrun::Outcome::Pass, row.pass_count),
+                    (testrun::Outcome::Failure, row.fail_count),
+                    (testrun::Outcome::Skip, row.skip_count),
+                ] {
+                    test.outcome = outcome;
+                    for _ in 0..count {
+                        writer.add_test_run(&test);
+                    }
+                }
+            }
+        }
+
+        Ok(writer)
+    }
+
+    /// Advances this writer's notion of "today" to `timestamp`, without touching any already
+    /// recorded columns itself. The next call that touches a given test's data day-shifts that
+    /// test's columns relative to the new `timestamp` exactly as it would if the run had simply
+    /// been observed later, so callers that need to replay several days' worth of runs (e.g.
+    /// [`Self::from_json_value`]) can step `timestamp` forward in place instead of rebuilding
+    /// the writer from scratch for each day.
+    #[cfg(feature = "serde")]
+    fn advance_to(&mut self, timestamp: u32) {
+        self.timestamp = timestamp;
+    }
+
     /// Merges the two parsed [`TestAnalytics`] into a writer.
     pub fn merge(
         a: &TestAnalytics,
@@ -104,9 +228,12 @@ impl TestAnalyticsWriter {
 
         writer.last_timestamp.reserve(expected_reserve);
         writer.last_duration.reserve(expected_reserve);
+        writer
+            .duration_histogram
+            .reserve(expected_reserve * NUM_DURATION_BUCKETS);
 
         for (smaller_idx, test) in smaller.tests.iter().enumerate() {
-            let name = StringTable::read(smaller.string_bytes, test.name_offset as usize)
+            let name = StringTable::read(smaller.string_bytes.as_ref(), test.name_offset as usize)
                 .map_err(|_| TestAnalyticsErrorKind::InvalidStringReference)?;
 
             let name_offset = writer.string_table.insert(name) as u32;
@@ -126,6 +253,9 @@ impl TestAnalyticsWriter {
 
                 writer.last_timestamp.resize(expected_size, 0);
                 writer.last_duration.resize(expected_size, 0.);
+                writer
+                    .duration_histogram
+                    .resize(expected_size * NUM_DURATION_BUCKETS, 0);
 
                 smaller_timestamp
             } else {
@@ -152,6 +282,10 @@ impl TestAnalyticsWriter {
                 shift_data(&mut writer.total_duration[range.clone()], today_offset);
                 shift_data(&mut writer.last_timestamp[range.clone()], today_offset);
                 shift_data(&mut writer.last_duration[range.clone()], today_offset);
+                shift_histogram_data(
+                    &mut writer.duration_histogram[bucketed(range)],
+                    today_offset,
+                );
 
                 data_idx..data_idx + overlap_len
             } else {
@@ -179,6 +313,12 @@ impl TestAnalyticsWriter {
                 &mut writer.total_duration[larger_range.clone()],
                 &smaller.total_duration[smaller_range.clone()],
             );
+            if smaller.header.num_buckets as usize == NUM_DURATION_BUCKETS {
+                add_assign_slice(
+                    &mut writer.duration_histogram[bucketed(larger_range.clone())],
+                    &smaller.duration_histogram[bucketed(smaller_range.clone())],
+                );
+            }
 
             let larger_last_timestamp = &mut writer.last_timestamp[larger_range.clone()]; // llt
             let larger_last_duration = &mut writer.last_duration[larger_range.clone()]; // lld
@@ -200,6 +340,170 @@ impl TestAnalyticsWriter {
         Ok(writer)
     }
 
+    /// Folds many parsed [`TestAnalytics`] files into one writer at once, for CI aggregation
+    /// jobs that need to combine dozens of per-shard files rather than just two.
+    ///
+    /// This recursively splits `files` in half, merges each half on a separate `rayon` task via
+    /// [`rayon::join`], then combines the two partial writers with [`Self::merge_writers`] —
+    /// the same balanced tree-reduction approach Solana's ledger verification uses for parallel
+    /// folds. The "insert smaller into larger" heuristic from [`Self::merge`] is preserved at
+    /// every combine step, to bound reallocation.
+    pub fn merge_many(files: &[TestAnalytics], timestamp: u32) -> Result<Self, TestAnalyticsError> {
+        if files.is_empty() {
+            return Ok(Self::new(0, timestamp));
+        }
+        if files.len() == 1 {
+            return Self::from_existing_format(&files[0], timestamp);
+        }
+
+        let mid = files.len() / 2;
+        let (left, right) = files.split_at(mid);
+        let (left, right) = rayon::join(
+            || Self::merge_many(left, timestamp),
+            || Self::merge_many(right, timestamp),
+        );
+        Ok(Self::merge_writers(left?, right?))
+    }
+
+    /// Merges two already-converted writers, inserting the smaller one's records into the
+    /// larger one.
+    ///
+    /// Unions their `IndexSet`s, resolving each of the smaller writer's test names through its
+    /// own string table and re-inserting it into the larger one's, then applies the same `offset_from_today` /
+    /// `adjust_selection_range` / `shift_data` alignment logic per record as [`Self::merge`].
+    /// Both writers always carry a `NUM_DURATION_BUCKETS`-wide duration histogram (unlike a
+    /// freshly-parsed [`TestAnalytics`], which may predate it), so unlike `merge` this never
+    /// needs to skip the histogram merge.
+    fn merge_writers(a: Self, b: Self) -> Self {
+        let (mut larger, smaller) = if (b.num_days, b.tests.len()) > (a.num_days, a.tests.len()) {
+            (b, a)
+        } else {
+            (a, b)
+        };
+
+        let expected_new = smaller.tests.len() / 4;
+        larger.tests.reserve(expected_new);
+        let expected_reserve = expected_new * larger.num_days;
+        larger.total_pass_count.reserve(expected_reserve);
+        larger.total_fail_count.reserve(expected_reserve);
+        larger.total_skip_count.reserve(expected_reserve);
+        larger.total_flaky_fail_count.reserve(expected_reserve);
+        larger.total_duration.reserve(expected_reserve);
+
+        larger.last_timestamp.reserve(expected_reserve);
+        larger.last_duration.reserve(expected_reserve);
+        larger
+            .duration_histogram
+            .reserve(expected_reserve * NUM_DURATION_BUCKETS);
+
+        let smaller_string_bytes = smaller.string_table.as_bytes();
+
+        for (smaller_idx, test) in smaller.tests.iter().enumerate() {
+            let name = StringTable::read(smaller_string_bytes, test.name_offset as usize)
+                .expect("a writer's own string table always resolves its own tests' offsets");
+
+            let name_offset = larger.string_table.insert(name) as u32;
+            let (idx, inserted) = larger.tests.insert_full(raw::Test { name_offset });
+
+            let data_idx = idx * larger.num_days;
+            let smaller_idx = smaller_idx * smaller.num_days;
+            let smaller_timestamp = smaller.last_timestamp[smaller_idx];
+
+            let last_timestamp = if inserted {
+                let expected_size = larger.tests.len() * larger.num_days;
+                larger.total_pass_count.resize(expected_size, 0);
+                larger.total_fail_count.resize(expected_size, 0);
+                larger.total_skip_count.resize(expected_size, 0);
+                larger.total_flaky_fail_count.resize(expected_size, 0);
+                larger.total_duration.resize(expected_size, 0.);
+
+                larger.last_timestamp.resize(expected_size, 0);
+                larger.last_duration.resize(expected_size, 0.);
+                larger
+                    .duration_histogram
+                    .resize(expected_size * NUM_DURATION_BUCKETS, 0);
+
+                smaller_timestamp
+            } else {
+                larger.last_timestamp[data_idx]
+            };
+
+            let today_offset = offset_from_today(last_timestamp, smaller_timestamp);
+            let smaller_range = adjust_selection_range(
+                smaller_idx..smaller_idx + smaller.num_days,
+                0..larger.num_days,
+                -today_offset.abs(),
+            );
+            let overlap_len = smaller_range.end - smaller_range.start;
+            // smaller has more recent data buckets, so we shift things around:
+            let larger_range = if today_offset < 0 {
+                let range = data_idx..data_idx + larger.num_days;
+                shift_data(&mut larger.total_pass_count[range.clone()], today_offset);
+                shift_data(&mut larger.total_fail_count[range.clone()], today_offset);
+                shift_data(&mut larger.total_skip_count[range.clone()], today_offset);
+                shift_data(
+                    &mut larger.total_flaky_fail_count[range.clone()],
+                    today_offset,
+                );
+                shift_data(&mut larger.total_duration[range.clone()], today_offset);
+                shift_data(&mut larger.last_timestamp[range.clone()], today_offset);
+                shift_data(&mut larger.last_duration[range.clone()], today_offset);
+                shift_histogram_data(
+                    &mut larger.duration_histogram[bucketed(range)],
+                    today_offset,
+                );
+
+                data_idx..data_idx + overlap_len
+            } else {
+                let idx_start = data_idx + today_offset as usize;
+                idx_start..idx_start + overlap_len
+            };
+
+            add_assign_slice(
+                &mut larger.total_pass_count[larger_range.clone()],
+                &smaller.total_pass_count[smaller_range.clone()],
+            );
+            add_assign_slice(
+                &mut larger.total_fail_count[larger_range.clone()],
+                &smaller.total_fail_count[smaller_range.clone()],
+            );
+            add_assign_slice(
+                &mut larger.total_skip_count[larger_range.clone()],
+                &smaller.total_skip_count[smaller_range.clone()],
+            );
+            add_assign_slice(
+                &mut larger.total_flaky_fail_count[larger_range.clone()],
+                &smaller.total_flaky_fail_count[smaller_range.clone()],
+            );
+            add_assign_slice(
+                &mut larger.total_duration[larger_range.clone()],
+                &smaller.total_duration[smaller_range.clone()],
+            );
+            add_assign_slice(
+                &mut larger.duration_histogram[bucketed(larger_range.clone())],
+                &smaller.duration_histogram[bucketed(smaller_range.clone())],
+            );
+
+            let larger_last_timestamp = &mut larger.last_timestamp[larger_range.clone()]; // llt
+            let larger_last_duration = &mut larger.last_duration[larger_range.clone()]; // lld
+            let smaller_last_timestamp = &smaller.last_timestamp[smaller_range.clone()]; // slt
+            let smaller_last_duration = &smaller.last_duration[smaller_range.clone()]; // sld
+            let iter = larger_last_timestamp
+                .iter_mut()
+                .zip(larger_last_duration.iter_mut())
+                .zip(smaller_last_timestamp)
+                .zip(smaller_last_duration);
+            for (((llt, lld), slt), sld) in iter {
+                if *llt <= *slt {
+                    *llt = *slt;
+                    *lld = *sld;
+                }
+            }
+        }
+
+        larger
+    }
+
     /// Does garbage collection by rewriting test records and throwing away those with expired data.
     ///
     /// This also makes sure that the data records are being truncated or extended to `num_days`.
@@ -237,6 +541,7 @@ impl TestAnalyticsWriter {
         let total_duration = mem::take(&mut self.total_duration);
         let last_timestamp = mem::take(&mut self.last_timestamp);
         let last_duration = mem::take(&mut self.last_duration);
+        let duration_histogram = mem::take(&mut self.duration_histogram);
 
         let expected_size = live_records * self.num_days;
         self.tests.reserve(live_records);
@@ -247,6 +552,8 @@ impl TestAnalyticsWriter {
         self.total_duration.reserve(expected_size);
         self.last_timestamp.reserve(expected_size);
         self.last_duration.reserve(expected_size);
+        self.duration_histogram
+            .reserve(expected_size * NUM_DURATION_BUCKETS);
 
         for ((old_idx, test), record_live) in tests.iter().enumerate().zip(record_liveness) {
             if !record_live {
@@ -277,6 +584,8 @@ impl TestAnalyticsWriter {
                 .extend_from_slice(&last_timestamp[old_range.clone()]);
             self.last_duration
                 .extend_from_slice(&last_duration[old_range.clone()]);
+            self.duration_histogram
+                .extend_from_slice(&duration_histogram[bucketed(old_range.clone())]);
 
             let expected_size = self.tests.len() * self.num_days;
             self.total_pass_count.resize(expected_size, 0);
@@ -286,13 +595,138 @@ impl TestAnalyticsWriter {
             self.total_duration.resize(expected_size, 0.);
             self.last_timestamp.resize(expected_size, 0);
             self.last_duration.resize(expected_size, 0.);
+            self.duration_histogram
+                .resize(expected_size * NUM_DURATION_BUCKETS, 0);
         }
 
         Ok(true)
     }
 
     /// Writes the data for the given [`Testrun`](testrun::Testrun) into this aggregation.
+    ///
+    /// This treats `test` as a run of one, crediting its outcome to `total_pass_count`/
+    /// `total_fail_count`/`total_skip_count` immediately. A lone outcome can never be flaky
+    /// (that needs both a fail and a pass for the same test within one run), so this is exactly
+    /// what [`Self::add_test_run_grouped`] followed by [`Self::finish_run`] would do too, just
+    /// without the scratch-map indirection.
     pub fn add_test_run(&mut self, test: &testrun::Testrun) {
+        let data_idx = self.record_test_run_data(test);
+        self.credit_duration(data_idx, test);
+        match test.outcome {
+            testrun::Outcome::Pass => self.total_pass_count[data_idx] += 1,
+            testrun::Outcome::Error | testrun::Outcome::Failure => {
+                self.total_fail_count[data_idx] += 1
+            }
+            testrun::Outcome::Skip => self.total_skip_count[data_idx] += 1,
+        }
+    }
+
+    /// Writes the data for one [`Testrun`](testrun::Testrun) observed as part of `run_id`, a
+    /// single CI run/commit that may execute the same test more than once (retries).
+    ///
+    /// Unlike [`Self::add_test_run`], neither the outcome nor the duration/histogram bucket is
+    /// credited right away: they're buffered in a scratch map keyed by test name, so a test that
+    /// both fails and later passes within the same run — the classic retry-turned-green signal
+    /// — can be recognized as flaky rather than just counted as an ordinary pass, and a test
+    /// retried several times within the run only contributes one duration/histogram sample
+    /// rather than one per retry. The representative duration is whichever `Testrun` was seen
+    /// first for this test in the run. Skips are not buffered: their outcome and duration are
+    /// credited immediately, as [`Self::add_test_run`] does. Call [`Self::finish_run`] once
+    /// every `Testrun` in `run_id` has been added, to actually commit the buffered outcomes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called with a different `run_id` before the previous run was finished via
+    /// [`Self::finish_run`].
+    pub fn add_test_run_grouped(&mut self, run_id: &str, test: &testrun::Testrun) {
+        match &self.current_run_id {
+            Some(current) => assert_eq!(
+                current, run_id,
+                "add_test_run_grouped called for a new run before the previous one was finished"
+            ),
+            None => self.current_run_id = Some(run_id.to_owned()),
+        }
+
+        let data_idx = self.record_test_run_data(test);
+        let duration = test.duration as f32;
+        let duration_bucket = super::format::bucket_for_duration_ms(
+            test.duration as f32 as f64 * 1000.,
+            NUM_DURATION_BUCKETS,
+        );
+        match test.outcome {
+            testrun::Outcome::Pass => {
+                self.current_run
+                    .entry(test.name.to_string())
+                    .or_insert(RunOutcome {
+                        data_idx,
+                        fail_count: 0,
+                        passed: false,
+                        duration,
+                        duration_bucket,
+                    })
+                    .passed = true;
+            }
+            testrun::Outcome::Error | testrun::Outcome::Failure => {
+                self.current_run
+                    .entry(test.name.to_string())
+                    .or_insert(RunOutcome {
+                        data_idx,
+                        fail_count: 0,
+                        passed: false,
+                        duration,
+                        duration_bucket,
+                    })
+                    .fail_count += 1;
+            }
+            // Skips aren't part of flake detection: there's no retry-turned-green signal to
+            // buffer, so credit them immediately just like `add_test_run` does.
+            testrun::Outcome::Skip => {
+                self.credit_duration(data_idx, test);
+                self.total_skip_count[data_idx] += 1
+            }
+        }
+    }
+
+    /// Commits every outcome buffered by [`Self::add_test_run_grouped`] since the last call to
+    /// this method, crediting each test exactly once to `total_pass_count`, `total_fail_count`,
+    /// `total_flaky_fail_count`, `total_duration`, and `duration_histogram` — so a flake, and a
+    /// retried test's duration, are each only counted once per run no matter how many times the
+    /// test was retried within it.
+    pub fn finish_run(&mut self) {
+        self.current_run_id = None;
+        for outcome in self.current_run.values() {
+            if outcome.passed {
+                self.total_pass_count[outcome.data_idx] += 1;
+                self.total_flaky_fail_count[outcome.data_idx] += outcome.fail_count;
+            } else {
+                self.total_fail_count[outcome.data_idx] += outcome.fail_count;
+            }
+            self.total_duration[outcome.data_idx] += outcome.duration;
+            self.duration_histogram
+                [outcome.data_idx * NUM_DURATION_BUCKETS + outcome.duration_bucket] += 1;
+        }
+        self.current_run.clear();
+    }
+
+    /// Credits `test`'s duration to `total_duration` and its histogram bucket, at `data_idx`.
+    /// Shared by [`Self::add_test_run`] (which calls this immediately) and [`Self::finish_run`]
+    /// (which calls the equivalent inline, once per test, for the buffered representative
+    /// sample of an [`Self::add_test_run_grouped`] run).
+    fn credit_duration(&mut self, data_idx: usize, test: &testrun::Testrun) {
+        self.total_duration[data_idx] += test.duration as f32;
+        let bucket = super::format::bucket_for_duration_ms(
+            test.duration as f32 as f64 * 1000.,
+            NUM_DURATION_BUCKETS,
+        );
+        self.duration_histogram[data_idx * NUM_DURATION_BUCKETS + bucket] += 1;
+    }
+
+    /// Resizes/day-shifts the backing columns as needed for one observed `test` run, and
+    /// records its last-run timestamp/duration. Returns the `data_idx` the test's today's-bucket
+    /// data lives at. Shared between [`Self::add_test_run`] and [`Self::add_test_run_grouped`],
+    /// which differ in when (and how many times) they credit `total_duration`/
+    /// `duration_histogram` and the pass/fail/flaky counts.
+    fn record_test_run_data(&mut self, test: &testrun::Testrun) -> usize {
         let name_offset = self.string_table.insert(&test.name) as u32;
         let (idx, inserted) = self.tests.insert_full(raw::Test { name_offset });
 
@@ -307,6 +741,8 @@ impl TestAnalyticsWriter {
 
             self.last_timestamp.resize(expected_size, 0);
             self.last_duration.resize(expected_size, 0.);
+            self.duration_histogram
+                .resize(expected_size * NUM_DURATION_BUCKETS, 0);
         } else {
             let range = data_idx..data_idx + self.num_days;
             let today_offset = offset_from_today(self.last_timestamp[data_idx], self.timestamp);
@@ -320,75 +756,273 @@ impl TestAnalyticsWriter {
             shift_data(&mut self.total_duration[range.clone()], today_offset);
             shift_data(&mut self.last_timestamp[range.clone()], today_offset);
             shift_data(&mut self.last_duration[range.clone()], today_offset);
+            shift_histogram_data(&mut self.duration_histogram[bucketed(range)], today_offset);
         }
 
-        self.total_duration[data_idx] += test.duration as f32;
-
         if self.last_timestamp[data_idx] <= self.timestamp {
             self.last_timestamp[data_idx] = self.timestamp;
             self.last_duration[data_idx] = test.duration as f32;
         }
 
-        match test.outcome {
-            testrun::Outcome::Pass => self.total_pass_count[data_idx] += 1,
-            testrun::Outcome::Error | testrun::Outcome::Failure => {
-                self.total_fail_count[data_idx] += 1
-            }
-            testrun::Outcome::Skip => self.total_skip_count[data_idx] += 1,
-        }
+        data_idx
     }
 
-    /// Serialize the converted data.
+    /// Serialize the converted data, without compressing any section.
     ///
-    /// This writes the [`TestAnalytics`] binary format into the given [`Write`].
+    /// This is the mmap-friendly path: every section can be borrowed directly out of the
+    /// written buffer on read, with no decompression step.
     pub fn serialize<W: Write>(self, writer: &mut W) -> std::io::Result<()> {
-        let mut writer = watto::Writer::new(writer);
+        self.serialize_compressed(writer, raw::CompressionType::None)
+    }
+
+    /// Serialize the converted data, optionally compressing each section.
+    ///
+    /// With [`raw::CompressionType::None`] this is identical to [`Self::serialize`]. With
+    /// `Lz4`/`Zstd`, every section (the count/timestamp/duration columns, the duration
+    /// histogram, and the string table) is individually compressed, each preceded by a
+    /// [`raw::SectionHeader`] giving the reader its uncompressed and compressed lengths. These
+    /// columns are usually mostly zero for a wide `num_days` window, so they compress well, at
+    /// the cost of the reader needing to decompress them into an owned buffer rather than
+    /// borrowing them out of an mmap.
+    pub fn serialize_compressed<W: Write>(
+        self,
+        writer: &mut W,
+        compression: raw::CompressionType,
+    ) -> std::io::Result<()> {
+        self.serialize_with(writer, compression, raw::ColumnEncoding::Fixed)
+    }
 
+    /// Serialize the converted data, choosing both the section compression and the encoding of
+    /// the five `u16` count columns.
+    ///
+    /// [`raw::ColumnEncoding::Varint`] is worth choosing for sparse matrices, where most counts
+    /// are zero or small, at the cost of the reader needing to incrementally decode the column
+    /// rather than borrowing it directly. See [`raw::ColumnEncoding`] for why it's always
+    /// written uncompressed regardless of `compression`.
+    ///
+    /// The payload is laid out into an in-memory buffer first, so `raw::Header::checksum` can
+    /// be computed over it and patched into the header before either is written out to `writer`.
+    pub fn serialize_with<W: Write>(
+        self,
+        writer: &mut W,
+        compression: raw::CompressionType,
+        encoding: raw::ColumnEncoding,
+    ) -> std::io::Result<()> {
+        let buf = self.layout_bytes(compression, encoding)?;
+        writer.write_all(&buf)
+    }
+
+    /// Serializes the converted data the same way [`Self::serialize`] does, but over an
+    /// [`AsyncWrite`][tokio::io::AsyncWrite] rather than blocking on [`std::io::Write`] — useful
+    /// for callers (e.g. uploading the blob to object storage) that would otherwise have to
+    /// block a runtime thread.
+    ///
+    /// Like [`Self::serialize_with`], `raw::Header::checksum` needs the full payload laid out
+    /// before the header can be written, so this still builds the same in-memory buffer
+    /// [`Self::serialize`] does; the difference is that buffer is then streamed out via
+    /// [`AsyncWriteExt::write_all`][tokio::io::AsyncWriteExt::write_all], which already chunks
+    /// itself over however many `poll_write` calls the writer needs and handles partial writes,
+    /// followed by a [`flush`][tokio::io::AsyncWriteExt::flush].
+    #[cfg(feature = "async")]
+    pub async fn serialize_async(
+        self,
+        mut writer: impl tokio::io::AsyncWrite + Unpin,
+    ) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt as _;
+
+        let buf = self.layout_bytes(raw::CompressionType::None, raw::ColumnEncoding::Fixed)?;
+        writer.write_all(&buf).await?;
+        writer.flush().await
+    }
+
+    /// Lays out the header and every section into a single in-memory buffer, patching
+    /// `raw::Header::checksum` into the header once the rest of the payload is known. Shared by
+    /// [`Self::serialize_with`] and [`Self::serialize_async`].
+    fn layout_bytes(
+        self,
+        compression: raw::CompressionType,
+        encoding: raw::ColumnEncoding,
+    ) -> std::io::Result<Vec<u8>> {
         let string_bytes = self.string_table.into_bytes();
+        let tests: Vec<raw::Test> = self.tests.into_iter().collect();
 
-        let header = raw::Header {
+        let mut header = raw::Header {
             magic: raw::TA_MAGIC,
             version: super::format::TA_VERSION,
 
             num_days: self.num_days as u32,
-            num_tests: self.tests.len() as u32,
+            num_tests: tests.len() as u32,
+
+            num_buckets: NUM_DURATION_BUCKETS as u32,
+            compression: compression as u32,
+            encoding: encoding as u32,
+            checksum: 0,
 
             string_bytes: string_bytes.len() as u32,
         };
+        let header_len = mem::size_of::<raw::Header>();
 
-        writer.write_all(header.as_bytes())?;
-        writer.align_to(8)?;
-
-        for test in self.tests.into_iter() {
-            writer.write_all(test.as_bytes())?;
-        }
-        writer.align_to(8)?;
+        let mut buf = Vec::new();
+        {
+            let mut buf_writer = watto::Writer::new(&mut buf);
 
-        writer.write_all(self.total_pass_count.as_bytes())?;
-        writer.align_to(8)?;
+            buf_writer.write_all(header.as_bytes())?;
+            buf_writer.align_to(8)?;
 
-        writer.write_all(self.total_fail_count.as_bytes())?;
-        writer.align_to(8)?;
+            for test in &tests {
+                buf_writer.write_all(test.as_bytes())?;
+            }
+            buf_writer.align_to(8)?;
 
-        writer.write_all(self.total_skip_count.as_bytes())?;
-        writer.align_to(8)?;
+            let sorted_index = sorted_name_index(&tests, &string_bytes);
+            for idx in &sorted_index {
+                buf_writer.write_all(idx.as_bytes())?;
+            }
+            buf_writer.align_to(8)?;
+
+            write_count_section(
+                &mut buf_writer,
+                &self.total_pass_count,
+                encoding,
+                compression,
+            )?;
+            write_count_section(
+                &mut buf_writer,
+                &self.total_fail_count,
+                encoding,
+                compression,
+            )?;
+            write_count_section(
+                &mut buf_writer,
+                &self.total_skip_count,
+                encoding,
+                compression,
+            )?;
+            write_count_section(
+                &mut buf_writer,
+                &self.total_flaky_fail_count,
+                encoding,
+                compression,
+            )?;
+            write_section(&mut buf_writer, &self.total_duration, compression)?;
+            write_section(&mut buf_writer, &self.last_timestamp, compression)?;
+            write_section(&mut buf_writer, &self.last_duration, compression)?;
+            write_count_section(
+                &mut buf_writer,
+                &self.duration_histogram,
+                encoding,
+                compression,
+            )?;
+            write_section(&mut buf_writer, &string_bytes, compression)?;
+        }
 
-        writer.write_all(self.total_flaky_fail_count.as_bytes())?;
-        writer.align_to(8)?;
+        header.checksum = crc32fast::hash(&buf[header_len..]);
+        buf[..header_len].copy_from_slice(header.as_bytes());
 
-        writer.write_all(self.total_duration.as_bytes())?;
-        writer.align_to(8)?;
+        Ok(buf)
+    }
+}
 
-        writer.write_all(self.last_timestamp.as_bytes())?;
-        writer.align_to(8)?;
+/// Writes one of the five `u16` count columns, honoring `encoding`.
+///
+/// [`raw::ColumnEncoding::Fixed`] delegates straight to [`write_section`], so `compression`
+/// still applies. [`raw::ColumnEncoding::Varint`] columns are always written uncompressed (see
+/// [`raw::ColumnEncoding`]), so `compression` is ignored for them.
+fn write_count_section<W: Write>(
+    writer: &mut watto::Writer<W>,
+    data: &[u16],
+    encoding: raw::ColumnEncoding,
+    compression: raw::CompressionType,
+) -> std::io::Result<()> {
+    match encoding {
+        raw::ColumnEncoding::Fixed => write_section(writer, data, compression),
+        raw::ColumnEncoding::Varint => write_varint_column(writer, data),
+    }
+}
 
-        writer.write_all(self.last_duration.as_bytes())?;
-        writer.align_to(8)?;
+/// Writes `data` as a length-prefixed unsigned-LEB128-varint stream: a `u32` byte length,
+/// followed by that many bytes of varint data, aligned to 8 bytes afterwards.
+fn write_varint_column<W: Write>(
+    writer: &mut watto::Writer<W>,
+    data: &[u16],
+) -> std::io::Result<()> {
+    let encoded = encode_varint_column(data);
+    writer.write_all((encoded.len() as u32).as_bytes())?;
+    writer.write_all(&encoded)?;
+    writer.align_to(8)?;
+    Ok(())
+}
 
-        writer.write_all(&string_bytes)?;
+/// Encodes `column` as a stream of unsigned LEB128 varints, one per value: 1 byte for the
+/// 0–127 range that dominates sparse count columns, growing to up to 3 bytes for `u16::MAX`.
+fn encode_varint_column(column: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(column.len());
+    for &value in column {
+        let mut value = u32::from(value);
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                break;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+    bytes
+}
 
-        Ok(())
+/// Writes one data section, aligning the following section to 8 bytes afterwards.
+///
+/// With [`raw::CompressionType::None`], this writes `data`'s raw bytes as-is. Otherwise, it
+/// compresses `data` and precedes the compressed bytes with a [`raw::SectionHeader`] so the
+/// reader knows how much to read and how large a buffer to decompress into.
+fn write_section<W: Write, T: Pod>(
+    writer: &mut watto::Writer<W>,
+    data: &[T],
+    compression: raw::CompressionType,
+) -> std::io::Result<()> {
+    let raw_bytes = data.as_bytes();
+
+    match compression {
+        raw::CompressionType::None => {
+            writer.write_all(raw_bytes)?;
+        }
+        raw::CompressionType::Lz4 => {
+            let compressed = lz4_flex::block::compress(raw_bytes);
+            let section_header = raw::SectionHeader {
+                uncompressed_len: raw_bytes.len() as u32,
+                compressed_len: compressed.len() as u32,
+            };
+            writer.write_all(section_header.as_bytes())?;
+            writer.write_all(&compressed)?;
+        }
+        raw::CompressionType::Zstd => {
+            let compressed = zstd::bulk::compress(raw_bytes, 0)?;
+            let section_header = raw::SectionHeader {
+                uncompressed_len: raw_bytes.len() as u32,
+                compressed_len: compressed.len() as u32,
+            };
+            writer.write_all(section_header.as_bytes())?;
+            writer.write_all(&compressed)?;
+        }
     }
+
+    writer.align_to(8)?;
+    Ok(())
+}
+
+/// Returns the indices of `tests`, sorted by [`test_sort_key`], for
+/// [`TestAnalytics::find_test`](super::format::TestAnalytics::find_test)/
+/// [`TestAnalytics::get`](super::format::TestAnalytics::get) to binary-search over.
+fn sorted_name_index(tests: &[raw::Test], string_bytes: &[u8]) -> Vec<u32> {
+    let mut sorted_index: Vec<u32> = (0..tests.len() as u32).collect();
+    sorted_index.sort_by(|&a, &b| {
+        let name_a = test_sort_key(&tests[a as usize], string_bytes);
+        let name_b = test_sort_key(&tests[b as usize], string_bytes);
+        name_a.cmp(name_b)
+    });
+    sorted_index
 }
 
 fn add_assign_slice<'a, T>(a: &'a mut [T], b: &'a [T])
@@ -399,3 +1033,87 @@ where
         *a += b;
     }
 }
+
+/// Converts a range of per-day data indices into the corresponding range of
+/// per-bucket `duration_histogram` indices.
+fn bucketed(range: Range<usize>) -> Range<usize> {
+    range.start * NUM_DURATION_BUCKETS..range.end * NUM_DURATION_BUCKETS
+}
+
+/// Day-shifts a `duration_histogram` slice the same way `shift_data` shifts the plain per-day
+/// count columns, except each "element" here is a contiguous run of `NUM_DURATION_BUCKETS`
+/// `u16`s rather than a single one. `shift_data` can't be reused directly since it shifts by
+/// element, not by bucket-stride.
+///
+/// `data` must cover exactly one test's per-day histogram (`num_days * NUM_DURATION_BUCKETS`
+/// entries). A negative `today_offset` pushes existing days further into the past, freeing up
+/// the most recent days (zeroed); a positive one drops the most recent days, pulling the older
+/// ones toward "today" (with the newly-exposed oldest days zeroed).
+fn shift_histogram_data(data: &mut [u16], today_offset: isize) {
+    if today_offset == 0 || data.is_empty() {
+        return;
+    }
+
+    let num_days = data.len() / NUM_DURATION_BUCKETS;
+    let shift_days = (today_offset.unsigned_abs()).min(num_days);
+    if shift_days == 0 {
+        return;
+    }
+
+    if today_offset < 0 {
+        data.copy_within(
+            0..(num_days - shift_days) * NUM_DURATION_BUCKETS,
+            shift_days * NUM_DURATION_BUCKETS,
+        );
+        data[..shift_days * NUM_DURATION_BUCKETS].fill(0);
+    } else {
+        data.copy_within(shift_days * NUM_DURATION_BUCKETS.., 0);
+        data[(num_days - shift_days) * NUM_DURATION_BUCKETS..].fill(0);
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use crate::testrun::{Outcome, PropertiesValue, Testrun};
+
+    use super::*;
+
+    fn sample_testrun() -> Testrun {
+        Testrun {
+            name: "test_something".into(),
+            classname: "some.module".into(),
+            duration: Some(1.5),
+            outcome: Outcome::Pass,
+            testsuite: "some.module".into(),
+            failure_message: None,
+            filename: None,
+            build_url: None,
+            computed_name: "test_something".into(),
+            properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
+        }
+    }
+
+    /// [`TestAnalyticsWriter::serialize_async`] must produce byte-for-byte the same output as
+    /// [`TestAnalyticsWriter::serialize`], and what it produces must parse back successfully.
+    #[tokio::test]
+    async fn test_serialize_async_matches_serialize() {
+        let mut sync_writer = TestAnalyticsWriter::new(2, 0);
+        sync_writer.add_test_run(&sample_testrun());
+        let mut sync_buf = vec![];
+        sync_writer.serialize(&mut sync_buf).unwrap();
+
+        let mut async_writer = TestAnalyticsWriter::new(2, 0);
+        async_writer.add_test_run(&sample_testrun());
+        let mut async_buf = vec![];
+        async_writer.serialize_async(&mut async_buf).await.unwrap();
+
+        assert_eq!(sync_buf, async_buf);
+
+        let parsed = TestAnalytics::parse(&async_buf, 0).unwrap();
+        assert_eq!(parsed.tests().count(), 1);
+    }
+}
@@ -1,14 +1,14 @@
 use std::{cmp::max, sync::OnceLock};
 
+use anyhow::{Context as _, Result};
 use pyo3::prelude::*;
 use regex::Regex;
 use serde::Serialize;
 use tera::{Context, Tera};
 
-
 #[pyfunction]
 pub fn escape_message(failure_message: &str) -> String {
-    /* 
+    /*
     Escapes characters that will break Markdown Templating.
      */
     let mut e = String::new();
@@ -62,6 +62,103 @@ pub fn shorten_file_paths(failure_message: &str) -> String {
     new
 }
 
+/// Which built-in template flavor to render `build_message`'s output as, when no explicit
+/// `template_dir`/`template` override is given.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Markdown,
+    PlainText,
+}
+
+impl OutputFormat {
+    fn builtin_template(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => include_str!("../templates/test_results_message.md"),
+            OutputFormat::PlainText => include_str!("../templates/test_results_message.txt"),
+        }
+    }
+
+    fn template_name(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "test_results_message.md",
+            OutputFormat::PlainText => "test_results_message.txt",
+        }
+    }
+}
+
+impl<'py> FromPyObject<'py> for OutputFormat {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s = ob.extract::<&str>()?;
+        match s {
+            "markdown" => Ok(OutputFormat::Markdown),
+            "plain_text" => Ok(OutputFormat::PlainText),
+            _ => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Invalid output format: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// How `build_message` should order failures before truncating to `max_failures`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FailureSortOrder {
+    #[default]
+    DurationAscending,
+    DurationDescending,
+}
+
+impl<'py> FromPyObject<'py> for FailureSortOrder {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s = ob.extract::<&str>()?;
+        match s {
+            "duration_ascending" => Ok(FailureSortOrder::DurationAscending),
+            "duration_descending" => Ok(FailureSortOrder::DurationDescending),
+            _ => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Invalid sort order: {}",
+                s
+            ))),
+        }
+    }
+}
+
+fn default_max_failures() -> usize {
+    3
+}
+
+/// Configuration for `build_message`, letting callers choose how many failures to include,
+/// in what order, which built-in flavor to render as, and where the Tera template itself
+/// comes from, so the renderer doesn't depend on the process's current working directory.
+///
+/// `template` takes priority over `template_dir`, which in turn takes priority over the
+/// built-in template selected by `output_format`.
+#[derive(FromPyObject, Debug, Clone)]
+pub struct BuildMessageOptions {
+    #[pyo3(default = "default_max_failures")]
+    pub max_failures: usize,
+    #[pyo3(default)]
+    pub sort_order: FailureSortOrder,
+    #[pyo3(default)]
+    pub output_format: OutputFormat,
+    #[pyo3(default)]
+    pub template_dir: Option<String>,
+    #[pyo3(default)]
+    pub template: Option<String>,
+}
+
+impl Default for BuildMessageOptions {
+    fn default() -> Self {
+        Self {
+            max_failures: default_max_failures(),
+            sort_order: FailureSortOrder::default(),
+            output_format: OutputFormat::default(),
+            template_dir: None,
+            template: None,
+        }
+    }
+}
+
 #[derive(FromPyObject, Debug, Clone)]
 pub struct Failure {
     name: String,
@@ -97,7 +194,14 @@ impl TemplateContext {
         failures: Vec<TemplateFailure>,
     ) -> Self {
         let num_output: i32 = failures.len().try_into().unwrap();
-        Self { num_tests, num_failed, num_passed, num_skipped, num_output, failures }
+        Self {
+            num_tests,
+            num_failed,
+            num_passed,
+            num_skipped,
+            num_output,
+            failures,
+        }
     }
 }
 
@@ -113,16 +217,23 @@ struct TemplateFailure {
 
 impl TemplateFailure {
     fn new(
-        test_suite: String, 
-        test_name: String, 
-        duration: String, 
-        raw_num_backticks: usize, 
+        test_suite: String,
+        test_name: String,
+        duration: String,
+        raw_num_backticks: usize,
         build_url: Option<String>,
-        stack_trace: Vec<String>
+        stack_trace: Vec<String>,
     ) -> Self {
         let num_backticks = max(raw_num_backticks + 1, 3);
         let backticks = String::from("`".repeat(num_backticks));
-        Self { test_suite, test_name, duration, backticks, build_url, stack_trace }
+        Self {
+            test_suite,
+            test_name,
+            duration,
+            backticks,
+            build_url,
+            stack_trace,
+        }
     }
 }
 
@@ -142,9 +253,41 @@ fn longest_repeated_substring(s: String, target: char) -> usize {
     max_length
 }
 
+/// Resolves the Tera template `build_message` should render, following `options`' priority
+/// order: an inline `template` string, then a `template_dir` to glob-load, then the built-in
+/// template for `options.output_format`. Returns the loaded `Tera` plus the name of the
+/// template to render within it.
+fn resolve_template(options: &BuildMessageOptions) -> Result<(Tera, &'static str)> {
+    if let Some(inline) = &options.template {
+        let mut tera = Tera::default();
+        tera.add_raw_template(options.output_format.template_name(), inline)
+            .context("Failed to parse inline template")?;
+        return Ok((tera, options.output_format.template_name()));
+    }
+
+    if let Some(template_dir) = &options.template_dir {
+        let glob = format!("{}/**/*", template_dir.trim_end_matches('/'));
+        let tera = Tera::new(&glob)
+            .with_context(|| format!("Failed to load templates from {}", template_dir))?;
+        return Ok((tera, options.output_format.template_name()));
+    }
+
+    let mut tera = Tera::default();
+    tera.add_raw_template(
+        options.output_format.template_name(),
+        options.output_format.builtin_template(),
+    )
+    .context("Failed to parse built-in template")?;
+    Ok((tera, options.output_format.template_name()))
+}
+
 #[pyfunction]
-pub fn build_message(payload: MessagePayload) -> String {
-    let tera = Tera::new("templates/**/*").unwrap();
+#[pyo3(signature = (payload, options=None))]
+pub fn build_message(
+    payload: MessagePayload,
+    options: Option<BuildMessageOptions>,
+) -> Result<String> {
+    let options = options.unwrap_or_default();
     let failed: i32 = payload.failed;
     let passed: i32 = payload.passed;
     let skipped: i32 = payload.skipped;
@@ -152,10 +295,17 @@ pub fn build_message(payload: MessagePayload) -> String {
     let completed = failed + passed + skipped;
 
     let mut sorted_failures: Vec<Failure> = payload.failures.to_vec();
-    sorted_failures.sort_by(|a, b| a.duration.partial_cmp(&b.duration).unwrap());
+    match options.sort_order {
+        FailureSortOrder::DurationAscending => {
+            sorted_failures.sort_by(|a, b| a.duration.partial_cmp(&b.duration).unwrap())
+        }
+        FailureSortOrder::DurationDescending => {
+            sorted_failures.sort_by(|a, b| b.duration.partial_cmp(&a.duration).unwrap())
+        }
+    }
 
     let mut template_failures: Vec<TemplateFailure> = Vec::new();
-    sorted_failures.truncate(3);
+    sorted_failures.truncate(options.max_failures);
     for failure in sorted_failures.iter_mut() {
         let failure_message = match failure.failure_message.as_ref() {
             Some(x) => String::from(x),
@@ -165,25 +315,26 @@ pub fn build_message(payload: MessagePayload) -> String {
             .split('\n')
             .map(|s| escape_message(s).to_string())
             .collect();
-        let num_backticks: usize = longest_repeated_substring(failure_message, '`'); 
+        let num_backticks: usize = longest_repeated_substring(failure_message, '`');
         let temp: TemplateFailure = TemplateFailure::new(
             failure.testsuite.clone(),
-            failure.name.clone(), 
+            failure.name.clone(),
             format!("{:.3}", failure.duration),
             num_backticks,
             failure.build_url.clone(),
             stack_trace_lines,
         );
         template_failures.push(temp);
-    };
-
-    let template_context = TemplateContext::new(
-        completed, failed, passed, skipped, template_failures,
-    );
-    
-    let message = tera.render(
-        "test_results_message.md", 
-        &Context::from_serialize(&template_context).unwrap())
-        .unwrap();
-    message
+    }
+
+    let template_context =
+        TemplateContext::new(completed, failed, passed, skipped, template_failures);
+
+    let (tera, template_name) = resolve_template(&options)?;
+    let context =
+        Context::from_serialize(&template_context).context("Failed to build template context")?;
+    let message = tera
+        .render(template_name, &context)
+        .context("Failed to render test results message template")?;
+    Ok(message)
 }
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use crate::raw_upload::parse_raw_upload;
+use crate::testrun::{Outcome, ParsingInfo};
+
+/// A test whose computed name showed inconsistent outcomes across the files in a single
+/// upload: it passed at least once and also failed or errored at least once, e.g. because a
+/// shard rerun or retry of the same suite produced a different result the second time.
+#[derive(Clone, Debug, Serialize, IntoPyObject, PartialEq)]
+pub struct FlakyTest {
+    pub name: String,
+    pub pass_count: usize,
+    pub fail_count: usize,
+}
+
+#[derive(Default)]
+struct OutcomeCounts {
+    pass_count: usize,
+    fail_count: usize,
+}
+
+/// Groups every testrun across `results` by its `computed_name` and flags any whose outcomes
+/// aren't consistent, i.e. at least one pass alongside at least one failure or error. Skipped
+/// testruns are counted towards neither outcome.
+///
+/// Results are sorted by name for stable output.
+pub fn detect_flaky_tests(results: &[ParsingInfo]) -> Vec<FlakyTest> {
+    let mut counts: HashMap<&str, OutcomeCounts> = HashMap::new();
+
+    for result in results {
+        for testrun in &result.testruns {
+            let entry = counts.entry(&testrun.computed_name).or_default();
+            match testrun.outcome {
+                Outcome::Pass => entry.pass_count += 1,
+                Outcome::Failure | Outcome::Error => entry.fail_count += 1,
+                Outcome::Skip => {}
+            }
+        }
+    }
+
+    let mut flaky_tests: Vec<FlakyTest> = counts
+        .into_iter()
+        .filter(|(_, counts)| counts.pass_count > 0 && counts.fail_count > 0)
+        .map(|(name, counts)| FlakyTest {
+            name: name.to_string(),
+            pass_count: counts.pass_count,
+            fail_count: counts.fail_count,
+        })
+        .collect();
+
+    flaky_tests.sort_by(|a, b| a.name.cmp(&b.name));
+    flaky_tests
+}
+
+/// Parses a raw upload and flags any flaky tests among its `test_results_files`, letting
+/// callers (e.g. to annotate a CI comment with a flaky section) skip re-running
+/// `parse_raw_upload` themselves just to get this.
+#[pyfunction]
+#[pyo3(signature = (raw_upload_bytes))]
+pub fn find_flaky_tests(raw_upload_bytes: &[u8]) -> anyhow::Result<Vec<FlakyTest>> {
+    let (results, _) = parse_raw_upload(raw_upload_bytes)?;
+    Ok(detect_flaky_tests(&results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testrun::{PropertiesValue, Testrun};
+    use crate::validated_string::ValidatedString;
+
+    fn make_testrun(computed_name: &str, outcome: Outcome) -> Testrun {
+        Testrun {
+            classname: ValidatedString::default(),
+            name: ValidatedString::default(),
+            duration: None,
+            outcome,
+            testsuite: ValidatedString::default(),
+            failure_message: None,
+            filename: None,
+            build_url: None,
+            computed_name: computed_name.try_into().unwrap(),
+            properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
+        }
+    }
+
+    fn make_parsing_info(testruns: Vec<Testrun>) -> ParsingInfo {
+        ParsingInfo {
+            framework: None,
+            testruns,
+            warnings: vec![],
+        }
+    }
+
+    #[test]
+    fn test_detect_flaky_tests_consistent_results_not_flaky() {
+        let results = vec![
+            make_parsing_info(vec![make_testrun("test_a", Outcome::Pass)]),
+            make_parsing_info(vec![make_testrun("test_a", Outcome::Pass)]),
+        ];
+        assert_eq!(detect_flaky_tests(&results), vec![]);
+    }
+
+    #[test]
+    fn test_detect_flaky_tests_pass_then_fail_is_flaky() {
+        let results = vec![
+            make_parsing_info(vec![make_testrun("test_a", Outcome::Pass)]),
+            make_parsing_info(vec![make_testrun("test_a", Outcome::Failure)]),
+        ];
+        let flaky = detect_flaky_tests(&results);
+        assert_eq!(flaky.len(), 1);
+        assert_eq!(flaky[0].name, "test_a");
+        assert_eq!(flaky[0].pass_count, 1);
+        assert_eq!(flaky[0].fail_count, 1);
+    }
+
+    #[test]
+    fn test_detect_flaky_tests_error_counts_as_failure() {
+        let results = vec![make_parsing_info(vec![
+            make_testrun("test_a", Outcome::Pass),
+            make_testrun("test_a", Outcome::Error),
+        ])];
+        let flaky = detect_flaky_tests(&results);
+        assert_eq!(flaky.len(), 1);
+        assert_eq!(flaky[0].pass_count, 1);
+        assert_eq!(flaky[0].fail_count, 1);
+    }
+
+    #[test]
+    fn test_detect_flaky_tests_skip_does_not_count_towards_either_outcome() {
+        let results = vec![make_parsing_info(vec![
+            make_testrun("test_a", Outcome::Pass),
+            make_testrun("test_a", Outcome::Skip),
+        ])];
+        assert_eq!(detect_flaky_tests(&results), vec![]);
+    }
+
+    #[test]
+    fn test_detect_flaky_tests_only_failures_not_flaky() {
+        let results = vec![make_parsing_info(vec![
+            make_testrun("test_a", Outcome::Failure),
+            make_testrun("test_a", Outcome::Failure),
+        ])];
+        assert_eq!(detect_flaky_tests(&results), vec![]);
+    }
+}
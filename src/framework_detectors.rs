@@ -1,173 +1,253 @@
-use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use pyo3::prelude::*;
+
+use crate::testrun::{check_substring_before_word_boundary, Framework};
+
+/// Weight contributed by a matching testsuite(s) name, the strongest signal since test
+/// runners usually stamp their own name directly on the suite.
+const SUITE_NAME_WEIGHT: f64 = 3.0;
+/// Weight contributed by a matching `<property>` value, e.g. an explicit `framework`/`language`/
+/// `runner`/`lang` key. Stronger than an extension match, since it's metadata a CI producer
+/// chose to record rather than something incidentally matching a token.
+const PROPERTIES_WEIGHT: f64 = 2.5;
+/// Weight contributed by a matching file extension, found in a filename, classname, or test name.
+const EXTENSION_WEIGHT: f64 = 2.0;
+/// Weight contributed by a matching failure message, the weakest signal since failure
+/// messages often just quote unrelated file paths or stack frames.
+const FAILURE_MESSAGE_WEIGHT: f64 = 1.0;
+
+/// The result of a framework detection pass: the most likely framework, how confident that
+/// guess is, and the full per-framework score breakdown it was computed from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameworkDetection {
+    pub framework: Framework,
+    /// The winning framework's score divided by the sum of all scores, in `[0, 1]`.
+    ///
+    /// A low confidence, i.e. multiple frameworks scoring similarly, usually means a
+    /// monorepo running more than one test framework rather than a wrong guess.
+    pub confidence: f64,
+    pub scores: HashMap<Framework, f64>,
+    /// Every other framework that scored above zero, in descending score order. Non-empty
+    /// runner-ups are a sign of an ambiguous, possibly multi-framework, test run.
+    pub runner_ups: Vec<(Framework, f64)>,
+}
 
-use crate::testrun::Framework;
+/// Sorts `scores` descending and splits off the winner from the runner-ups.
+///
+/// Returns `None` if `scores` is empty.
+fn rank_scores(scores: HashMap<Framework, f64>) -> Option<(Framework, f64, Vec<(Framework, f64)>)> {
+    let mut ranked: Vec<(Framework, f64)> = scores.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut ranked = ranked.into_iter();
+    let (framework, score) = ranked.next()?;
+    Some((framework, score, ranked.collect()))
+}
 
-fn gen_reg(s: &str) -> Regex {
-    Regex::new(format!(r"(?i){}(\W|$)", s).as_str()).unwrap()
+/// A rule describing how to recognize one [`Framework`], used by [`FrameworkRegistry`].
+///
+/// Built-in rules cover pytest, Jest, Vitest, and PHPUnit; register additional rules via
+/// [`FrameworkRegistry::register`] (or [`register_framework_rule`]) to add further tokens,
+/// extensions, or message patterns. A rule's `framework` is always one of the [`Framework`]
+/// variants, so runners without a variant of their own (e.g. Go test or RSpec) cannot be
+/// reported.
+#[derive(Clone, Debug)]
+pub struct FrameworkRule {
+    pub framework: Framework,
+    /// Word-boundary tokens matched against the enclosing testsuite(s) name, the strongest
+    /// signal since test runners usually stamp their own name directly on the suite.
+    pub name_tokens: Vec<String>,
+    /// Word-boundary tokens matched against a classname, filename, or test name.
+    pub extensions: Vec<String>,
+    /// Word-boundary tokens matched against a free-text failure message, the weakest signal
+    /// since failure messages often just quote unrelated file paths or stack frames.
+    pub message_patterns: Vec<String>,
 }
 
-fn apply_reg(rl: &[(Regex, Framework)], v: Vec<String>) -> Option<Framework> {
-    for (r, f) in rl {
-        for s in v.iter() {
-            if r.is_match(s) {
-                return Some(*f);
-            }
+impl FrameworkRule {
+    fn score(&self, signals: &DetectionSignals) -> f64 {
+        let mut score = 0.0;
+        if matches_any(&self.name_tokens, &signals.testsuite_names) {
+            score += SUITE_NAME_WEIGHT;
+        }
+        // Properties are matched against the same tokens as the testsuite name, since an
+        // explicit `framework`/`language`/`runner`/`lang` property quotes the framework by
+        // name just like a testsuite name would.
+        if matches_any(&self.name_tokens, &signals.property_values) {
+            score += PROPERTIES_WEIGHT;
         }
+        if matches_any(&self.extensions, &signals.extension_sources) {
+            score += EXTENSION_WEIGHT;
+        }
+        if matches_any(&self.message_patterns, &signals.messages) {
+            score += FAILURE_MESSAGE_WEIGHT;
+        }
+        score
     }
-    None
 }
 
-fn get_framework_names() -> [(Regex, Framework); 4] {
-    [
-        (gen_reg("pytest"), Framework::Pytest),
-        (gen_reg("jest"), Framework::Jest),
-        (gen_reg("vitest"), Framework::Vitest),
-        (gen_reg("phpunit"), Framework::PHPUnit),
-    ]
+fn matches_any(tokens: &[String], haystacks: &[&str]) -> bool {
+    tokens.iter().any(|token| {
+        haystacks
+            .iter()
+            .any(|haystack| check_substring_before_word_boundary(haystack, &token.to_lowercase()))
+    })
 }
 
-fn get_file_extensions() -> [(Regex, Framework); 2] {
-    [
-        (gen_reg(".py"), Framework::Pytest),
-        (gen_reg(".php"), Framework::PHPUnit),
+fn builtin_rules() -> Vec<FrameworkRule> {
+    vec![
+        FrameworkRule {
+            framework: Framework::Pytest,
+            name_tokens: vec!["pytest".to_string()],
+            extensions: vec![".py".to_string()],
+            message_patterns: vec![".py".to_string()],
+        },
+        FrameworkRule {
+            framework: Framework::Vitest,
+            name_tokens: vec!["vitest".to_string()],
+            extensions: vec![],
+            message_patterns: vec![],
+        },
+        FrameworkRule {
+            framework: Framework::Jest,
+            name_tokens: vec!["jest".to_string()],
+            extensions: vec![],
+            message_patterns: vec![],
+        },
+        FrameworkRule {
+            framework: Framework::PHPUnit,
+            name_tokens: vec!["phpunit".to_string()],
+            extensions: vec![".php".to_string()],
+            message_patterns: vec![".php".to_string()],
+        },
     ]
 }
 
-// i want it to iterate through running certain regexes on all
-pub fn detect_framework(
-    testsuites_name: String,
-    mut testsuite_names: Vec<String>,
-    mut filenames: Vec<String>,
-    example_class_name: String,
-    example_test_name: String,
-    failure_messages: Vec<String>,
-) -> Option<Framework> {
-    let framework_names = get_framework_names();
-    testsuite_names.insert(0, testsuites_name);
-    match apply_reg(&framework_names, testsuite_names) {
-        Some(f) => return Some(f),
-        None => {}
-    };
-
-    // is there a better way to do something like this
-    let file_extensions = get_file_extensions();
-    filenames.push(example_class_name);
-    filenames.push(example_test_name);
-    filenames.extend(failure_messages.into_iter());
-
-    match apply_reg(&file_extensions, filenames) {
-        Some(f) => return Some(f),
-        None => {}
-    };
+/// The signals a [`FrameworkRegistry`] scores a [`FrameworkRule`] against, gathered from a
+/// single testrun (or, for [`check_testsuites_name`], just a `<testsuites>` name).
+#[derive(Clone, Debug, Default)]
+pub struct DetectionSignals<'a> {
+    pub testsuite_names: Vec<&'a str>,
+    /// Every string value nested inside the testrun's `properties`, e.g. an explicit
+    /// `framework`/`language`/`runner`/`lang` key, but also anything else a CI producer
+    /// happened to record.
+    pub property_values: Vec<&'a str>,
+    pub extension_sources: Vec<&'a str>,
+    pub messages: Vec<&'a str>,
+}
 
-    None
+/// The minimum score a framework must reach before [`FrameworkRegistry::detect`] will commit
+/// to it, rather than returning `None` for a testrun with no recognizable signal at all.
+///
+/// Set to the weakest signal's weight, so a single matching signal of any kind is enough.
+pub const DEFAULT_THRESHOLD: f64 = FAILURE_MESSAGE_WEIGHT;
+
+/// An extensible collection of [`FrameworkRule`]s, scored against a testrun's signals to
+/// detect which framework produced it.
+///
+/// This is the one framework-detection engine in the crate: [`Testrun::framework`],
+/// [`Testrun::detect_framework`], [`check_testsuites_name`], and JUnit parsing all resolve
+/// through a registry (see [`detect_with_global_registry`]) rather than scoring anything
+/// themselves.
+///
+/// [`Testrun::framework`]: crate::testrun::Testrun::framework
+/// [`Testrun::detect_framework`]: crate::testrun::Testrun::detect_framework
+#[derive(Clone, Debug)]
+pub struct FrameworkRegistry {
+    rules: Vec<FrameworkRule>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_detect_framework_empty() {
-        assert_eq!(
-            detect_framework(
-                "".to_string(),
-                vec![],
-                vec![],
-                "".to_string(),
-                "".to_string(),
-                vec![]
-            ),
-            None
-        );
+impl Default for FrameworkRegistry {
+    fn default() -> Self {
+        Self {
+            rules: builtin_rules(),
+        }
     }
+}
 
-    #[test]
-    fn test_detect_framework_testsuites_name() {
-        assert_eq!(
-            detect_framework(
-                "jest tests".to_string(),
-                vec![],
-                vec![],
-                "".to_string(),
-                "".to_string(),
-                vec![]
-            ),
-            Some(Framework::Jest)
-        );
+impl FrameworkRegistry {
+    /// Adds a custom rule, in addition to the built-in pytest/Jest/Vitest/PHPUnit ones.
+    pub fn register(&mut self, rule: FrameworkRule) {
+        self.rules.push(rule);
     }
 
-    #[test]
-    fn test_detect_framework_testsuite_names() {
-        assert_eq!(
-            detect_framework(
-                "".to_string(),
-                vec!["pytest".to_string()],
-                vec![],
-                "".to_string(),
-                "".to_string(),
-                vec![]
-            ),
-            Some(Framework::Pytest)
-        );
-    }
+    /// Scores every registered rule against `signals`, returning the highest scorer (and its
+    /// runner-ups) if its score reaches `threshold`, or `None` if nothing matched at all.
+    pub fn detect(&self, signals: &DetectionSignals, threshold: f64) -> Option<FrameworkDetection> {
+        let mut scores: HashMap<Framework, f64> = HashMap::new();
+        for rule in &self.rules {
+            let score = rule.score(signals);
+            if score > 0.0 {
+                *scores.entry(rule.framework).or_insert(0.0) += score;
+            }
+        }
 
-    #[test]
-    fn test_detect_framework_filenames() {
-        assert_eq!(
-            detect_framework(
-                "".to_string(),
-                vec![],
-                vec![".py".to_string()],
-                "".to_string(),
-                "".to_string(),
-                vec![]
-            ),
-            Some(Framework::Pytest)
-        );
-    }
+        let total: f64 = scores.values().sum();
+        let scores_snapshot = scores.clone();
+        let (framework, score, runner_ups) = rank_scores(scores)?;
+        if score < threshold {
+            return None;
+        }
 
-    #[test]
-    fn test_detect_framework_example_classname() {
-        assert_eq!(
-            detect_framework(
-                "".to_string(),
-                vec![],
-                vec![],
-                ".py".to_string(),
-                "".to_string(),
-                vec![]
-            ),
-            Some(Framework::Pytest)
-        );
+        Some(FrameworkDetection {
+            framework,
+            confidence: score / total,
+            scores: scores_snapshot,
+            runner_ups,
+        })
     }
+}
 
-    #[test]
-    fn test_detect_framework_example_name() {
-        assert_eq!(
-            detect_framework(
-                "".to_string(),
-                vec![],
-                vec![],
-                "".to_string(),
-                ".py".to_string(),
-                vec![]
-            ),
-            Some(Framework::Pytest)
-        );
-    }
-    #[test]
-    fn test_detect_framework_failure_messages() {
-        assert_eq!(
-            detect_framework(
-                "".to_string(),
-                vec![],
-                vec![],
-                "".to_string(),
-                "".to_string(),
-                vec![".py".to_string()]
-            ),
-            Some(Framework::Pytest)
-        );
-    }
+fn global_registry() -> &'static Mutex<FrameworkRegistry> {
+    static REGISTRY: OnceLock<Mutex<FrameworkRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(FrameworkRegistry::default()))
+}
+
+/// Registers a custom [`FrameworkRule`] with the process-wide registry used by
+/// [`Testrun::framework`](crate::testrun::Testrun::framework) and [`check_testsuites_name`],
+/// so callers can extend detection without patching the crate.
+pub fn register_framework_rule(rule: FrameworkRule) {
+    global_registry().lock().unwrap().register(rule);
+}
+
+/// Detects the framework behind `signals` using the process-wide registry.
+pub fn detect_with_global_registry(signals: &DetectionSignals) -> Option<FrameworkDetection> {
+    global_registry()
+        .lock()
+        .unwrap()
+        .detect(signals, DEFAULT_THRESHOLD)
+}
+
+/// Identifies the framework from a `<testsuites>` element's `name` attribute alone.
+pub fn check_testsuites_name(testsuites_name: &str) -> Option<Framework> {
+    let signals = DetectionSignals {
+        testsuite_names: vec![testsuites_name],
+        ..Default::default()
+    };
+    detect_with_global_registry(&signals).map(|detection| detection.framework)
+}
+
+/// Registers a custom framework-detection rule, so `Testrun.framework()` and
+/// `parse_junit_xml` also weigh its tokens. `framework` must be one of `"Pytest"`,
+/// `"Vitest"`, `"Jest"`, or `"PHPUnit"`; any other name raises a `ValueError`.
+///
+/// `name_tokens`, `extensions`, and `message_patterns` are matched case-insensitively, each as
+/// a token that must be followed by a word boundary (a preceding boundary is not required),
+/// against, respectively, the testsuite name, a classname/filename/test name, and a failure
+/// message -- in that order of decreasing signal strength.
+#[pyfunction]
+#[pyo3(signature = (framework, name_tokens, extensions=vec![], message_patterns=vec![]))]
+pub fn register_custom_framework(
+    framework: Framework,
+    name_tokens: Vec<String>,
+    extensions: Vec<String>,
+    message_patterns: Vec<String>,
+) {
+    register_framework_rule(FrameworkRule {
+        framework,
+        name_tokens,
+        extensions,
+        message_patterns,
+    });
 }
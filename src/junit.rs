@@ -1,23 +1,27 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use pyo3::prelude::*;
 use serde_json::Value;
 use std::collections::HashSet;
 use std::fmt;
+use std::io::BufRead;
 
 use quick_xml::events::attributes::{Attribute, Attributes};
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::reader::Reader;
 
 use crate::compute_name::{compute_name, unescape_str};
-use crate::testrun::{check_testsuites_name, Framework, Outcome, PropertiesValue, Testrun};
+use crate::testrun::{
+    check_testsuites_name, Framework, Outcome, PropertiesValue, RerunInfo, RerunKind, Testrun,
+};
 use crate::validated_string::ValidatedString;
 use crate::warning::WarningInfo;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 enum ParseAttrsError {
-    #[error("Limit of string is 1000 chars, for {0}, we got {1}")]
-    AttrTooLong(&'static str, usize),
+    #[error("Limit of string is {0} chars, for {1}, we got {2}")]
+    AttrTooLong(usize, &'static str, usize),
     #[error("Error converting attribute {0} to UTF-8 string")]
     ConversionError(&'static str),
     #[error("Missing name attribute in testcase")]
@@ -26,6 +30,42 @@ enum ParseAttrsError {
     ParseError,
 }
 
+/// What to do with a string attribute that exceeds [`ParseOptions::max_string_len`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlongStringPolicy {
+    /// Truncate to `max_string_len` and keep the testcase.
+    Truncate,
+    /// Emit a [`WarningInfo`] and skip the whole testcase.
+    WarnAndSkip,
+    /// Fail the entire parse.
+    Error,
+}
+
+/// Knobs controlling parse limits that used to be hard-coded magic constants, such as
+/// [`ValidatedString`]'s 1000-character cap and the warn-and-skip behavior on over-long
+/// attributes.
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    /// Maximum length allowed for a `ValidatedString` field.
+    pub max_string_len: usize,
+    /// What to do when a string attribute exceeds `max_string_len`.
+    pub on_string_too_long: OverlongStringPolicy,
+    /// Whether `property` elements outside the `evals.*` namespace are kept in
+    /// `Testrun.properties` (flatly, keyed by their `name` attribute) instead of being
+    /// silently discarded.
+    pub collect_all_properties: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            max_string_len: 1000,
+            on_string_too_long: OverlongStringPolicy::WarnAndSkip,
+            collect_all_properties: false,
+        }
+    }
+}
+
 fn convert_attribute(attribute: Attribute) -> Result<String> {
     let bytes = attribute.value.into_owned();
     Ok(String::from_utf8(bytes)?)
@@ -34,25 +74,73 @@ fn convert_attribute(attribute: Attribute) -> Result<String> {
 fn extract_validated_string(
     attribute: Attribute,
     field_name: &'static str,
+    options: &ParseOptions,
 ) -> Result<ValidatedString, ParseAttrsError> {
-    let unvalidated_string =
+    let mut unvalidated_string =
         convert_attribute(attribute).map_err(|_| ParseAttrsError::ConversionError(field_name))?;
     let string_len = unvalidated_string.len();
-    ValidatedString::from_string(unvalidated_string)
-        .map_err(|_| ParseAttrsError::AttrTooLong(field_name, string_len))
+    if string_len > options.max_string_len
+        && options.on_string_too_long == OverlongStringPolicy::Truncate
+    {
+        let mut end = options.max_string_len;
+        while end > 0 && !unvalidated_string.is_char_boundary(end) {
+            end -= 1;
+        }
+        unvalidated_string.truncate(end);
+    }
+    ValidatedString::from_string_with_limit(unvalidated_string, options.max_string_len)
+        .map_err(|_| ParseAttrsError::AttrTooLong(options.max_string_len, field_name, string_len))
 }
 
 struct TestcaseAttrs {
     name: ValidatedString,
     time: Option<String>,
+    timestamp: Option<String>,
     classname: Option<ValidatedString>,
     file: Option<ValidatedString>,
 }
 
+/// Parses a `timestamp` attribute leniently, accepting RFC-3339/ISO-8601 strings. A timestamp
+/// without a UTC offset is taken to be in UTC.
+fn parse_timestamp(raw: &str) -> chrono::ParseResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(raw)
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|err| {
+            chrono::NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S%.f")
+                .map(|naive| DateTime::from_naive_utc_and_offset(naive, Utc))
+                .map_err(|_| err)
+        })
+}
+
+/// The `tests`/`failures`/`errors`/`skipped`/`assertions` summary attributes a `<testsuite>`
+/// element declares about its children, used to sanity-check the counts we actually tally.
+#[derive(Default, Clone, Copy)]
+struct DeclaredTestsuiteCounts {
+    tests: Option<u64>,
+    failures: Option<u64>,
+    errors: Option<u64>,
+    skipped: Option<u64>,
+    assertions: Option<u64>,
+}
+
+fn parse_testsuite_counts(e: &BytesStart) -> Result<DeclaredTestsuiteCounts> {
+    Ok(DeclaredTestsuiteCounts {
+        tests: get_attribute(e, "tests")?.and_then(|s| s.parse().ok()),
+        failures: get_attribute(e, "failures")?.and_then(|s| s.parse().ok()),
+        errors: get_attribute(e, "errors")?.and_then(|s| s.parse().ok()),
+        skipped: get_attribute(e, "skipped")?.and_then(|s| s.parse().ok()),
+        assertions: get_attribute(e, "assertions")?.and_then(|s| s.parse().ok()),
+    })
+}
+
 // originally from https://gist.github.com/scott-codecov/311c174ecc7de87f7d7c50371c6ef927#file-cobertura-rs-L18-L31
-fn parse_testcase_attrs(attributes: Attributes) -> Result<TestcaseAttrs, ParseAttrsError> {
+fn parse_testcase_attrs(
+    attributes: Attributes,
+    options: &ParseOptions,
+) -> Result<TestcaseAttrs, ParseAttrsError> {
     let mut name: Option<ValidatedString> = None;
     let mut time: Option<String> = None;
+    let mut timestamp: Option<String> = None;
     let mut classname: Option<ValidatedString> = None;
     let mut file: Option<ValidatedString> = None;
 
@@ -66,14 +154,20 @@ fn parse_testcase_attrs(attributes: Attributes) -> Result<TestcaseAttrs, ParseAt
                         .map_err(|_| ParseAttrsError::ConversionError("time"))?,
                 );
             }
+            b"timestamp" => {
+                timestamp = Some(
+                    convert_attribute(attribute)
+                        .map_err(|_| ParseAttrsError::ConversionError("timestamp"))?,
+                );
+            }
             b"classname" => {
-                classname = Some(extract_validated_string(attribute, "classname")?);
+                classname = Some(extract_validated_string(attribute, "classname", options)?);
             }
             b"name" => {
-                name = Some(extract_validated_string(attribute, "name")?);
+                name = Some(extract_validated_string(attribute, "name", options)?);
             }
             b"file" => {
-                file = Some(extract_validated_string(attribute, "file")?);
+                file = Some(extract_validated_string(attribute, "file", options)?);
             }
             _ => {}
         }
@@ -83,6 +177,7 @@ fn parse_testcase_attrs(attributes: Attributes) -> Result<TestcaseAttrs, ParseAt
         Some(name) => Ok(TestcaseAttrs {
             name,
             time,
+            timestamp,
             classname,
             file,
         }),
@@ -106,8 +201,12 @@ fn populate(
     rel_attrs: TestcaseAttrs,
     testsuite: ValidatedString,
     testsuite_time: Option<&str>,
+    testsuite_timestamp: Option<&str>,
     framework: Option<Framework>,
     network: Option<&HashSet<String>>,
+    options: &ParseOptions,
+    warnings: &mut Vec<WarningInfo>,
+    warning_location: (u64, usize, usize),
 ) -> Result<(Testrun, Option<Framework>)> {
     let name = rel_attrs.name;
     let classname = rel_attrs.classname.unwrap_or_default();
@@ -118,6 +217,24 @@ fn populate(
         .and_then(|t| t.parse().ok());
     let file = rel_attrs.file;
 
+    let timestamp = rel_attrs
+        .timestamp
+        .as_deref()
+        .or(testsuite_timestamp)
+        .and_then(|raw| match parse_timestamp(raw) {
+            Ok(dt) => Some(dt),
+            Err(_) => {
+                let (pos, line, column) = warning_location;
+                warnings.push(WarningInfo::new(
+                    format!("Could not parse timestamp attribute: {raw}"),
+                    pos,
+                    line,
+                    column,
+                ));
+                None
+            }
+        });
+
     let mut t = Testrun {
         name,
         classname,
@@ -129,9 +246,28 @@ fn populate(
         build_url: None,
         computed_name: ValidatedString::default(),
         properties: PropertiesValue(None),
+        system_out: None,
+        system_err: None,
+        reruns: Vec::new(),
+        timestamp,
     };
 
-    let framework = framework.or_else(|| t.framework());
+    let framework = framework.or_else(|| {
+        let detection = t.detect_framework()?;
+        if !detection.runner_ups.is_empty() {
+            let (pos, line, column) = warning_location;
+            warnings.push(WarningInfo::new(
+                format!(
+                    "Ambiguous framework detection: picked {:?} over {:?}",
+                    detection.framework, detection.runner_ups
+                ),
+                pos,
+                line,
+                column,
+            ));
+        }
+        Some(detection.framework)
+    });
     let computed_name = compute_name(
         &t.classname,
         &t.name,
@@ -139,8 +275,9 @@ fn populate(
         t.filename.as_deref(),
         network,
     );
-    t.computed_name = ValidatedString::from_string(computed_name)
-        .context("Error converting computed name to ValidatedString")?;
+    t.computed_name =
+        ValidatedString::from_string_with_limit(computed_name, options.max_string_len)
+            .context("Error converting computed name to ValidatedString")?;
 
     Ok((t, framework))
 }
@@ -161,6 +298,39 @@ pub fn get_position_info(input: &[u8], byte_offset: usize) -> (usize, usize) {
     (line, column)
 }
 
+/// Resolves byte offsets into 1-based `(line, column)` pairs incrementally, one `read_event_into`
+/// buffer at a time, so `use_reader` doesn't need the whole document in memory to report warning
+/// locations (unlike [`get_position_info`], which rescans a fully-buffered input each call).
+#[derive(Default)]
+struct PositionTracker {
+    line: usize,
+    // byte offset one past the most recently seen `\n`
+    line_start: u64,
+}
+
+impl PositionTracker {
+    fn new() -> Self {
+        Self {
+            line: 1,
+            line_start: 0,
+        }
+    }
+
+    fn advance(&mut self, start: u64, bytes: &[u8]) {
+        for (i, &b) in bytes.iter().enumerate() {
+            if b == b'\n' {
+                self.line += 1;
+                self.line_start = start + i as u64 + 1;
+            }
+        }
+    }
+
+    fn resolve(&self, pos: u64) -> (usize, usize) {
+        let column = (pos.saturating_sub(self.line_start)) as usize + 1;
+        (self.line, column)
+    }
+}
+
 #[derive(Error, Debug)]
 struct NotEvalsPropertyError;
 
@@ -194,15 +364,32 @@ impl fmt::Display for NotEvalsPropertyError {
 ///         }
 ///     }
 /// }
-fn parse_property_element(e: &BytesStart, existing_properties: &mut PropertiesValue) -> Result<()> {
-    // Early return if not an evals property
-    let name = get_attribute(e, "name")?
-        .filter(|n| n.starts_with("evals"))
-        .ok_or(NotEvalsPropertyError)?;
-
+///
+/// Properties outside the `evals.*` namespace are discarded unless
+/// [`ParseOptions::collect_all_properties`] is set, in which case they're kept flatly under
+/// their own `name` attribute instead.
+fn parse_property_element(
+    e: &BytesStart,
+    existing_properties: &mut PropertiesValue,
+    options: &ParseOptions,
+) -> Result<()> {
+    let name = get_attribute(e, "name")?.ok_or(NotEvalsPropertyError)?;
     let value = get_attribute(e, "value")?
         .ok_or_else(|| anyhow::anyhow!("Property must have value attribute"))?;
 
+    if !name.starts_with("evals") {
+        if !options.collect_all_properties {
+            return Err(NotEvalsPropertyError.into());
+        }
+        if existing_properties.0.is_none() {
+            *existing_properties = PropertiesValue(Some(serde_json::json!({})));
+        }
+        if let Value::Object(map) = existing_properties.0.as_mut().unwrap() {
+            map.insert(name, Value::String(value));
+        }
+        return Ok(());
+    }
+
     let name_parts: Vec<&str> = name.split(".").collect();
     if name_parts.len() < 2 {
         anyhow::bail!("Property name must have at least 2 parts");
@@ -260,10 +447,58 @@ enum TestrunOrSkipped {
     Skipped,
 }
 
+/// Tracks which element is currently accumulating text content, generalizing the old
+/// `in_failure`/`in_error` flags to also cover `system-out`/`system-err` and the
+/// Surefire/Gradle `rerun*`/`flaky*` sub-elements.
+enum TextTarget {
+    Failure,
+    Error,
+    SystemOut,
+    SystemErr,
+    /// Index into the currently saved testrun's `reruns` vec.
+    Rerun(usize),
+}
+
+fn rerun_kind_for_tag(tag: &[u8]) -> Option<RerunKind> {
+    match tag {
+        b"rerunFailure" => Some(RerunKind::RerunFailure),
+        b"rerunError" => Some(RerunKind::RerunError),
+        b"flakyFailure" => Some(RerunKind::FlakyFailure),
+        b"flakyError" => Some(RerunKind::FlakyError),
+        _ => None,
+    }
+}
+
+/// Pushes a new [`RerunInfo`] onto the currently saved testrun, returning its index so
+/// subsequent text events can be routed to it.
+fn push_rerun(
+    saved_testrun: &mut Option<TestrunOrSkipped>,
+    e: &BytesStart,
+    kind: RerunKind,
+) -> Result<Option<usize>> {
+    let message = get_attribute(e, "message")?;
+    let exception_type = get_attribute(e, "type")?;
+
+    match saved_testrun {
+        Some(TestrunOrSkipped::Testrun(testrun)) => {
+            testrun.reruns.push(RerunInfo {
+                kind,
+                message,
+                exception_type,
+                stack_trace: None,
+            });
+            Ok(Some(testrun.reruns.len() - 1))
+        }
+        _ => Ok(None),
+    }
+}
+
 fn handle_property_element(
     e: &BytesStart,
     saved_testrun: &mut Option<TestrunOrSkipped>,
     buffer_position: u64,
+    position: &PositionTracker,
+    options: &ParseOptions,
     warnings: &mut Vec<WarningInfo>,
 ) -> Result<()> {
     // Check if there is a testrun currently being processed
@@ -274,11 +509,14 @@ fn handle_property_element(
         .as_mut()
         .context("Error accessing saved testrun")?;
     if let TestrunOrSkipped::Testrun(testrun) = saved {
-        if let Err(e) = parse_property_element(e, &mut testrun.properties) {
+        if let Err(e) = parse_property_element(e, &mut testrun.properties, options) {
             if !e.is::<NotEvalsPropertyError>() {
+                let (line, column) = position.resolve(buffer_position);
                 warnings.push(WarningInfo::new(
                     format!("Error parsing `property` element: {}", e),
                     buffer_position,
+                    line,
+                    column,
                 ));
             }
         }
@@ -286,15 +524,15 @@ fn handle_property_element(
     Ok(())
 }
 
-pub fn use_reader(
-    reader: &mut Reader<&[u8]>,
+pub fn use_reader<R: BufRead>(
+    reader: &mut Reader<R>,
     network: Option<&HashSet<String>>,
+    options: &ParseOptions,
 ) -> PyResult<(Option<Framework>, Vec<Testrun>, Vec<WarningInfo>)> {
     let mut testruns: Vec<Testrun> = Vec::new();
     let mut saved_testrun: Option<TestrunOrSkipped> = None;
 
-    let mut in_failure: bool = false;
-    let mut in_error: bool = false;
+    let mut open_text_target: Option<TextTarget> = None;
 
     let mut framework: Option<Framework> = None;
 
@@ -305,21 +543,39 @@ pub fn use_reader(
     // else append a clone of the last value in the vec
     let mut testsuite_names: Vec<Option<ValidatedString>> = vec![];
     let mut testsuite_times: Vec<Option<String>> = vec![];
+    let mut testsuite_timestamps: Vec<Option<String>> = vec![];
+
+    // `<system-out>`/`<system-err>` found directly under a `<testsuite>` (rather than a
+    // `<testcase>`) apply to every testcase in that suite. We remember where each suite's
+    // testcases start in `testruns` and backfill them once the suite closes.
+    let mut testsuite_testrun_start: Vec<usize> = vec![];
+    let mut testsuite_system_out: Vec<Option<String>> = vec![];
+    let mut testsuite_system_err: Vec<Option<String>> = vec![];
+
+    // declared `tests`/`failures`/`errors`/`skipped`/`assertions` attributes, reconciled
+    // against the actually-tallied outcomes once the suite closes.
+    let mut testsuite_counts: Vec<DeclaredTestsuiteCounts> = vec![];
+
+    let mut position = PositionTracker::new();
 
     let mut buf = Vec::new();
     loop {
+        let event_start = reader.buffer_position();
         let event = reader
             .read_event_into(&mut buf)
             .context("Error parsing XML")?;
+        position.advance(event_start, &buf);
         match event {
             Event::Eof => {
                 break;
             }
             Event::Start(e) => match e.name().as_ref() {
                 b"testcase" => {
-                    let attrs = parse_testcase_attrs(e.attributes());
+                    let attrs = parse_testcase_attrs(e.attributes(), options);
                     match attrs {
                         Ok(attrs) => {
+                            let warning_pos = reader.buffer_position() - e.len() as u64;
+                            let (line, column) = position.resolve(warning_pos);
                             let (testrun, parsed_framework) = populate(
                                 attrs,
                                 testsuite_names
@@ -328,17 +584,27 @@ pub fn use_reader(
                                     .find_map(|e| e.clone())
                                     .unwrap_or_default(),
                                 testsuite_times.iter().rev().find_map(|e| e.as_deref()),
+                                testsuite_timestamps.iter().rev().find_map(|e| e.as_deref()),
                                 framework,
                                 network,
+                                options,
+                                &mut warnings,
+                                (warning_pos, line, column),
                             )?;
                             saved_testrun = Some(TestrunOrSkipped::Testrun(testrun));
                             framework = parsed_framework;
                         }
                         Err(error) => match error {
-                            ParseAttrsError::AttrTooLong(_, _) => {
+                            ParseAttrsError::AttrTooLong(_, _, _)
+                                if options.on_string_too_long != OverlongStringPolicy::Error =>
+                            {
+                                let warning_pos = reader.buffer_position() - e.len() as u64;
+                                let (line, column) = position.resolve(warning_pos);
                                 warnings.push(WarningInfo::new(
                                     format!("Warning while parsing testcase attributes: {}", error),
-                                    reader.buffer_position() - e.len() as u64,
+                                    warning_pos,
+                                    line,
+                                    column,
                                 ));
                                 saved_testrun = Some(TestrunOrSkipped::Skipped);
                             }
@@ -376,7 +642,7 @@ pub fn use_reader(
                         TestrunOrSkipped::Skipped => {}
                     }
 
-                    in_error = true;
+                    open_text_target = Some(TextTarget::Error);
                 }
                 b"failure" => {
                     let saved = saved_testrun
@@ -392,7 +658,14 @@ pub fn use_reader(
                         TestrunOrSkipped::Skipped => {}
                     }
 
-                    in_failure = true;
+                    open_text_target = Some(TextTarget::Failure);
+                }
+                tag @ (b"rerunFailure" | b"rerunError" | b"flakyFailure" | b"flakyError") => {
+                    if let Some(kind) = rerun_kind_for_tag(tag) {
+                        if let Some(idx) = push_rerun(&mut saved_testrun, &e, kind)? {
+                            open_text_target = Some(TextTarget::Rerun(idx));
+                        }
+                    }
                 }
                 b"testsuite" => {
                     testsuite_names.push(
@@ -404,7 +677,21 @@ pub fn use_reader(
                             .transpose()?,
                     );
                     testsuite_times.push(get_attribute(&e, "time")?);
+                    testsuite_timestamps.push(get_attribute(&e, "timestamp")?);
+                    testsuite_testrun_start.push(testruns.len());
+                    testsuite_system_out.push(None);
+                    testsuite_system_err.push(None);
+                    testsuite_counts.push(parse_testsuite_counts(&e)?);
+                }
+                b"system-out" if open_text_target.is_none() => {
+                    open_text_target = Some(TextTarget::SystemOut)
                 }
+                b"system-err" if open_text_target.is_none() => {
+                    open_text_target = Some(TextTarget::SystemErr)
+                }
+                // nested inside a `rerun*`/`flaky*` element; its text is folded into that
+                // rerun's `stack_trace` rather than the testcase's own system output.
+                b"system-out" | b"system-err" => {}
                 b"testsuites" => {
                     let testsuites_name = get_attribute(&e, "name")?;
                     framework = testsuites_name.and_then(|name| check_testsuites_name(&name))
@@ -413,6 +700,8 @@ pub fn use_reader(
                     &e,
                     &mut saved_testrun,
                     reader.buffer_position(),
+                    &position,
+                    options,
                     &mut warnings,
                 )?,
                 _ => {}
@@ -427,19 +716,119 @@ pub fn use_reader(
                         TestrunOrSkipped::Skipped => {}
                     }
                 }
-                b"failure" => in_failure = false,
-                b"error" => in_error = false,
+                b"failure" if matches!(open_text_target, Some(TextTarget::Failure)) => {
+                    open_text_target = None
+                }
+                b"error" if matches!(open_text_target, Some(TextTarget::Error)) => {
+                    open_text_target = None
+                }
+                b"system-out" if matches!(open_text_target, Some(TextTarget::SystemOut)) => {
+                    open_text_target = None
+                }
+                b"system-err" if matches!(open_text_target, Some(TextTarget::SystemErr)) => {
+                    open_text_target = None
+                }
+                b"rerunFailure" | b"rerunError" | b"flakyFailure" | b"flakyError" => {
+                    open_text_target = None
+                }
                 b"testsuite" => {
                     testsuite_times.pop();
+                    testsuite_timestamps.pop();
                     testsuite_names.pop();
+
+                    let start = testsuite_testrun_start.pop().unwrap_or(0);
+                    let suite_system_out = testsuite_system_out.pop().flatten();
+                    let suite_system_err = testsuite_system_err.pop().flatten();
+                    for testrun in testruns[start..].iter_mut() {
+                        if testrun.system_out.is_none() {
+                            if let Some(system_out) = &suite_system_out {
+                                testrun.system_out =
+                                    Some(ValidatedString::from_str(system_out).context(
+                                        "Error converting system-out to ValidatedString",
+                                    )?);
+                            }
+                        }
+                        if testrun.system_err.is_none() {
+                            if let Some(system_err) = &suite_system_err {
+                                testrun.system_err =
+                                    Some(ValidatedString::from_str(system_err).context(
+                                        "Error converting system-err to ValidatedString",
+                                    )?);
+                            }
+                        }
+                    }
+
+                    let declared = testsuite_counts.pop().unwrap_or_default();
+                    let mut mismatches = Vec::new();
+                    if let Some(declared_tests) = declared.tests {
+                        let actual = (testruns.len() - start) as u64;
+                        if declared_tests != actual {
+                            mismatches
+                                .push(format!("tests: declared {declared_tests}, found {actual}"));
+                        }
+                    }
+                    if let Some(declared_failures) = declared.failures {
+                        let actual = testruns[start..]
+                            .iter()
+                            .filter(|t| t.outcome == Outcome::Failure)
+                            .count() as u64;
+                        if declared_failures != actual {
+                            mismatches.push(format!(
+                                "failures: declared {declared_failures}, found {actual}"
+                            ));
+                        }
+                    }
+                    if let Some(declared_errors) = declared.errors {
+                        let actual = testruns[start..]
+                            .iter()
+                            .filter(|t| t.outcome == Outcome::Error)
+                            .count() as u64;
+                        if declared_errors != actual {
+                            mismatches.push(format!(
+                                "errors: declared {declared_errors}, found {actual}"
+                            ));
+                        }
+                    }
+                    if let Some(declared_skipped) = declared.skipped {
+                        let actual = testruns[start..]
+                            .iter()
+                            .filter(|t| t.outcome == Outcome::Skip)
+                            .count() as u64;
+                        if declared_skipped != actual {
+                            mismatches.push(format!(
+                                "skipped: declared {declared_skipped}, found {actual}"
+                            ));
+                        }
+                    }
+
+                    if !mismatches.is_empty() {
+                        let assertions_note = declared
+                            .assertions
+                            .map(|assertions| format!("; assertions declared: {assertions}"))
+                            .unwrap_or_default();
+                        let pos = reader.buffer_position();
+                        let (line, column) = position.resolve(pos);
+                        warnings.push(WarningInfo::new(
+                            format!(
+                                "testsuite summary mismatch ({}){}",
+                                mismatches.join(", "),
+                                assertions_note
+                            ),
+                            pos,
+                            line,
+                            column,
+                        ));
+                    }
                 }
                 _ => (),
             },
             Event::Empty(e) => match e.name().as_ref() {
                 b"testcase" => {
-                    let attrs = parse_testcase_attrs(e.attributes());
+                    let attrs = parse_testcase_attrs(e.attributes(), options);
                     match attrs {
                         Ok(attrs) => {
+                            let warning_pos = reader.buffer_position() - e.len() as u64;
+                            let (line, column) = position.resolve(warning_pos);
                             let (testrun, parsed_framework) = populate(
                                 attrs,
                                 testsuite_names
@@ -448,17 +837,27 @@ pub fn use_reader(
                                     .find_map(|e| e.clone())
                                     .unwrap_or_default(),
                                 testsuite_times.iter().rev().find_map(|e| e.as_deref()),
+                                testsuite_timestamps.iter().rev().find_map(|e| e.as_deref()),
                                 framework,
                                 network,
+                                options,
+                                &mut warnings,
+                                (warning_pos, line, column),
                             )?;
                             testruns.push(testrun);
                             framework = parsed_framework;
                         }
                         Err(error) => match error {
-                            ParseAttrsError::AttrTooLong(_, _) => {
+                            ParseAttrsError::AttrTooLong(_, _, _)
+                                if options.on_string_too_long != OverlongStringPolicy::Error =>
+                            {
+                                let warning_pos = reader.buffer_position() - e.len() as u64;
+                                let (line, column) = position.resolve(warning_pos);
                                 warnings.push(WarningInfo::new(
                                     format!("Warning while parsing testcase attributes: {}", error),
-                                    reader.buffer_position() - e.len() as u64,
+                                    warning_pos,
+                                    line,
+                                    column,
                                 ));
                             }
                             _ => Err(anyhow::anyhow!(
@@ -511,28 +910,87 @@ pub fn use_reader(
                     &e,
                     &mut saved_testrun,
                     reader.buffer_position(),
+                    &position,
+                    options,
                     &mut warnings,
                 )?,
+                tag @ (b"rerunFailure" | b"rerunError" | b"flakyFailure" | b"flakyError") => {
+                    if let Some(kind) = rerun_kind_for_tag(tag) {
+                        push_rerun(&mut saved_testrun, &e, kind)?;
+                    }
+                }
                 _ => {}
             },
-            Event::Text(mut xml_failure_message) => {
-                if in_failure || in_error {
+            Event::Text(mut xml_text) => match &open_text_target {
+                Some(TextTarget::Failure) | Some(TextTarget::Error) => {
                     let saved = saved_testrun
                         .as_mut()
                         .context("Error accessing saved testrun")?;
                     match saved {
                         TestrunOrSkipped::Testrun(testrun) => {
-                            xml_failure_message.inplace_trim_end();
-                            xml_failure_message.inplace_trim_start();
+                            xml_text.inplace_trim_end();
+                            xml_text.inplace_trim_start();
 
-                            testrun.failure_message = Some(
-                                unescape_str(std::str::from_utf8(&xml_failure_message)?).into(),
-                            );
+                            testrun.failure_message =
+                                Some(unescape_str(std::str::from_utf8(&xml_text)?).into());
                         }
                         TestrunOrSkipped::Skipped => {}
                     }
                 }
-            }
+                Some(TextTarget::SystemOut) | Some(TextTarget::SystemErr) => {
+                    let is_system_out = matches!(open_text_target, Some(TextTarget::SystemOut));
+                    xml_text.inplace_trim_end();
+                    xml_text.inplace_trim_start();
+                    let text = unescape_str(std::str::from_utf8(&xml_text)?).into_owned();
+
+                    match saved_testrun.as_mut() {
+                        // `<system-out>`/`<system-err>` nested directly inside `<testcase>`.
+                        Some(TestrunOrSkipped::Testrun(testrun)) => {
+                            if is_system_out {
+                                testrun.system_out =
+                                    Some(ValidatedString::from_string(text).context(
+                                        "Error converting system-out to ValidatedString",
+                                    )?);
+                            } else {
+                                testrun.system_err =
+                                    Some(ValidatedString::from_string(text).context(
+                                        "Error converting system-err to ValidatedString",
+                                    )?);
+                            }
+                        }
+                        Some(TestrunOrSkipped::Skipped) => {}
+                        // `<system-out>`/`<system-err>` nested directly inside `<testsuite>`,
+                        // applies to every testcase in the suite once it closes.
+                        None => {
+                            if is_system_out {
+                                if let Some(slot) = testsuite_system_out.last_mut() {
+                                    *slot = Some(text);
+                                }
+                            } else if let Some(slot) = testsuite_system_err.last_mut() {
+                                *slot = Some(text);
+                            }
+                        }
+                    }
+                }
+                Some(TextTarget::Rerun(idx)) => {
+                    let idx = *idx;
+                    if let Some(TestrunOrSkipped::Testrun(testrun)) = saved_testrun.as_mut() {
+                        xml_text.inplace_trim_end();
+                        xml_text.inplace_trim_start();
+                        let text = unescape_str(std::str::from_utf8(&xml_text)?).into_owned();
+
+                        if let Some(rerun) = testrun.reruns.get_mut(idx) {
+                            // multiple nested elements (`stackTrace`, `system-out`) can
+                            // contribute text to the same rerun; fold them together.
+                            rerun.stack_trace = Some(match rerun.stack_trace.take() {
+                                Some(existing) => format!("{existing}\n{text}"),
+                                None => text,
+                            });
+                        }
+                    }
+                }
+                None => {}
+            },
 
             // There are several other `Event`s we do not consider here
             _ => (),
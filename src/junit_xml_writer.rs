@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use anyhow::Result;
+use pyo3::prelude::*;
+use quick_xml::events::{BytesCData, BytesEnd, BytesStart, Event};
+use quick_xml::writer::Writer;
+
+use crate::failure_message::escape_message;
+use crate::testrun::{Outcome, ParsingInfo, Testrun};
+
+/// Groups every testrun across `results` by its `testsuite`, preserving the order each
+/// testsuite was first seen in.
+fn group_by_testsuite(results: &[ParsingInfo]) -> Vec<(&str, Vec<&Testrun>)> {
+    let mut order: Vec<&str> = Vec::new();
+    let mut groups: HashMap<&str, Vec<&Testrun>> = HashMap::new();
+
+    for result in results {
+        for testrun in &result.testruns {
+            let suite = &*testrun.testsuite;
+            groups.entry(suite).or_default().push(testrun);
+            if !order.contains(&suite) {
+                order.push(suite);
+            }
+        }
+    }
+
+    order
+        .into_iter()
+        .map(|suite| (suite, groups.remove(suite).unwrap_or_default()))
+        .collect()
+}
+
+fn write_testcase(writer: &mut Writer<Cursor<Vec<u8>>>, testrun: &Testrun) -> Result<()> {
+    let mut testcase = BytesStart::new("testcase");
+    // The name is already run through `compute_name`, so it's consistent regardless of which
+    // framework originally produced this testrun.
+    testcase.push_attribute(("name", &*testrun.computed_name));
+    testcase.push_attribute(("classname", &*testrun.classname));
+    if let Some(duration) = testrun.duration {
+        testcase.push_attribute(("time", format!("{:.3}", duration).as_str()));
+    }
+    writer.write_event(Event::Start(testcase))?;
+
+    match testrun.outcome {
+        Outcome::Failure | Outcome::Error => {
+            let tag = if testrun.outcome == Outcome::Failure {
+                "failure"
+            } else {
+                "error"
+            };
+            let message = testrun
+                .failure_message
+                .as_deref()
+                .map(escape_message)
+                .unwrap_or_default();
+
+            let mut element = BytesStart::new(tag);
+            element.push_attribute(("message", message.as_str()));
+            writer.write_event(Event::Start(element.clone()))?;
+            writer.write_event(Event::CData(BytesCData::new(message.as_str())))?;
+            writer.write_event(Event::End(BytesEnd::new(tag)))?;
+        }
+        Outcome::Skip => {
+            writer.write_event(Event::Empty(BytesStart::new("skipped")))?;
+        }
+        Outcome::Pass => {}
+    }
+
+    if let Some(system_out) = &testrun.system_out {
+        writer.write_event(Event::Start(BytesStart::new("system-out")))?;
+        writer.write_event(Event::CData(BytesCData::new(system_out)))?;
+        writer.write_event(Event::End(BytesEnd::new("system-out")))?;
+    }
+    if let Some(system_err) = &testrun.system_err {
+        writer.write_event(Event::Start(BytesStart::new("system-err")))?;
+        writer.write_event(Event::CData(BytesCData::new(system_err)))?;
+        writer.write_event(Event::End(BytesEnd::new("system-err")))?;
+    }
+
+    writer.write_event(Event::End(BytesEnd::new("testcase")))?;
+    Ok(())
+}
+
+fn write_testsuite(
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    name: &str,
+    testruns: &[&Testrun],
+) -> Result<()> {
+    let failures = testruns
+        .iter()
+        .filter(|t| t.outcome == Outcome::Failure)
+        .count();
+    let errors = testruns
+        .iter()
+        .filter(|t| t.outcome == Outcome::Error)
+        .count();
+    let skipped = testruns
+        .iter()
+        .filter(|t| t.outcome == Outcome::Skip)
+        .count();
+
+    let mut testsuite = BytesStart::new("testsuite");
+    testsuite.push_attribute(("name", name));
+    testsuite.push_attribute(("tests", testruns.len().to_string().as_str()));
+    testsuite.push_attribute(("failures", failures.to_string().as_str()));
+    testsuite.push_attribute(("errors", errors.to_string().as_str()));
+    testsuite.push_attribute(("skipped", skipped.to_string().as_str()));
+    writer.write_event(Event::Start(testsuite))?;
+
+    for testrun in testruns {
+        write_testcase(writer, testrun)?;
+    }
+
+    writer.write_event(Event::End(BytesEnd::new("testsuite")))?;
+    Ok(())
+}
+
+/// Re-serializes a batch of parsed `ParsingInfo` results into a single normalized `<testsuites>`
+/// JUnit XML document, grouping testruns by testsuite regardless of which file or framework
+/// they originally came from. Test names are already `compute_name`'d and failure messages are
+/// run through `escape_message` here, so the output is consistent even after merging heterogeneous
+/// inputs (Jest/Pytest/Vitest/PHPUnit).
+#[pyfunction]
+#[pyo3(signature = (results))]
+pub fn write_junit_xml(results: Vec<ParsingInfo>) -> Result<Vec<u8>> {
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+
+    writer.write_event(Event::Start(BytesStart::new("testsuites")))?;
+    for (name, testruns) in group_by_testsuite(&results) {
+        write_testsuite(&mut writer, name, &testruns)?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("testsuites")))?;
+
+    Ok(writer.into_inner().into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testrun::PropertiesValue;
+    use crate::validated_string::ValidatedString;
+
+    fn make_testrun(
+        testsuite: &str,
+        computed_name: &str,
+        outcome: Outcome,
+        failure_message: Option<&str>,
+    ) -> Testrun {
+        Testrun {
+            classname: "ClassName".try_into().unwrap(),
+            name: ValidatedString::default(),
+            duration: Some(1.5),
+            outcome,
+            testsuite: testsuite.try_into().unwrap(),
+            failure_message: failure_message.map(|s| s.to_string()),
+            filename: None,
+            build_url: None,
+            computed_name: computed_name.try_into().unwrap(),
+            properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
+        }
+    }
+
+    fn make_parsing_info(testruns: Vec<Testrun>) -> ParsingInfo {
+        ParsingInfo {
+            framework: None,
+            testruns,
+            warnings: vec![],
+        }
+    }
+
+    #[test]
+    fn test_write_junit_xml_contains_testsuites_and_testcases() {
+        let results = vec![make_parsing_info(vec![
+            make_testrun("suite_a", "test_one", Outcome::Pass, None),
+            make_testrun("suite_a", "test_two", Outcome::Failure, Some("boom")),
+        ])];
+
+        let xml = String::from_utf8(write_junit_xml(results).unwrap()).unwrap();
+        assert!(xml.contains("<testsuites>"));
+        assert!(xml.contains(
+            r#"<testsuite name="suite_a" tests="2" failures="1" errors="0" skipped="0">"#
+        ));
+        assert!(xml.contains(r#"name="test_one""#));
+        assert!(xml.contains(r#"name="test_two""#));
+        assert!(xml.contains("<failure message=\"boom\">"));
+        assert!(xml.contains("<![CDATA[boom]]>"));
+    }
+
+    #[test]
+    fn test_write_junit_xml_groups_by_testsuite_across_results() {
+        let results = vec![
+            make_parsing_info(vec![make_testrun(
+                "suite_a",
+                "test_one",
+                Outcome::Pass,
+                None,
+            )]),
+            make_parsing_info(vec![make_testrun(
+                "suite_a",
+                "test_two",
+                Outcome::Pass,
+                None,
+            )]),
+        ];
+
+        let xml = String::from_utf8(write_junit_xml(results).unwrap()).unwrap();
+        assert_eq!(xml.matches("<testsuite ").count(), 1);
+        assert!(xml.contains(r#"tests="2""#));
+    }
+
+    #[test]
+    fn test_write_junit_xml_skipped_testcase() {
+        let results = vec![make_parsing_info(vec![make_testrun(
+            "suite_a",
+            "test_one",
+            Outcome::Skip,
+            None,
+        )])];
+
+        let xml = String::from_utf8(write_junit_xml(results).unwrap()).unwrap();
+        assert!(xml.contains("<skipped/>"));
+    }
+}
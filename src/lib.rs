@@ -2,8 +2,12 @@ use pyo3::exceptions::PyException;
 use pyo3::prelude::*;
 
 mod failure_message;
+mod flaky;
+mod framework_detectors;
 mod junit;
+mod junit_xml_writer;
 mod pytest_reportlog;
+mod raw_upload;
 mod testrun;
 mod vitest_json;
 
@@ -28,6 +32,12 @@ fn test_results_parser(py: Python, m: &Bound<PyModule>) -> PyResult<()> {
         m
     )?)?;
     m.add_function(wrap_pyfunction!(failure_message::shorten_file_paths, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        framework_detectors::register_custom_framework,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(flaky::find_flaky_tests, m)?)?;
+    m.add_function(wrap_pyfunction!(junit_xml_writer::write_junit_xml, m)?)?;
 
     Ok(())
 }
@@ -5,20 +5,77 @@ use pyo3::prelude::*;
 use std::collections::HashSet;
 use std::io::prelude::*;
 
-use flate2::bufread::ZlibDecoder;
+use flate2::bufread::{GzDecoder, ZlibDecoder};
 
 use quick_xml::reader::Reader;
 use serde::Deserialize;
 
-use crate::junit::{get_position_info, use_reader};
+use crate::junit::{get_position_info, use_reader, ParseOptions};
 use crate::testrun::ParsingInfo;
 use crate::warning::WarningInfo;
 
+/// How a [`TestResultFile`]'s `data` is encoded, so producers that don't (or can't) zlib-compress
+/// their upload can still skip a pre-processing step.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+enum TestResultFileFormat {
+    /// Base64-encoded, zlib-compressed. The original, and still the default, encoding.
+    #[serde(rename = "base64+compressed")]
+    Base64Compressed,
+    /// Base64-encoded, gzip-compressed.
+    #[serde(rename = "base64+gzip")]
+    Base64Gzip,
+    /// Base64-encoded, uncompressed.
+    #[serde(rename = "base64")]
+    Base64,
+    /// Raw UTF-8 XML, neither encoded nor compressed.
+    #[serde(rename = "plain")]
+    Plain,
+}
+
+fn default_format() -> TestResultFileFormat {
+    TestResultFileFormat::Base64Compressed
+}
+
 #[derive(Deserialize, Debug, Clone)]
 struct TestResultFile {
     filename: String,
+    #[serde(default = "default_format")]
+    format: TestResultFileFormat,
     data: String,
 }
+
+/// Decodes `file.data` into the raw XML bytes it represents, per its `format`.
+fn decode_file_data(file: &TestResultFile) -> anyhow::Result<Vec<u8>> {
+    if file.format == TestResultFileFormat::Plain {
+        return Ok(file.data.as_bytes().to_vec());
+    }
+
+    let decoded_bytes = BASE64_STANDARD
+        .decode(&file.data)
+        .context("Error decoding base64")?;
+
+    match file.format {
+        TestResultFileFormat::Base64Compressed => {
+            let mut decoder = ZlibDecoder::new(decoded_bytes.as_slice());
+            let mut decompressed_bytes = Vec::new();
+            decoder
+                .read_to_end(&mut decompressed_bytes)
+                .context("Error decompressing file")?;
+            Ok(decompressed_bytes)
+        }
+        TestResultFileFormat::Base64Gzip => {
+            let mut decoder = GzDecoder::new(decoded_bytes.as_slice());
+            let mut decompressed_bytes = Vec::new();
+            decoder
+                .read_to_end(&mut decompressed_bytes)
+                .context("Error decompressing file")?;
+            Ok(decompressed_bytes)
+        }
+        TestResultFileFormat::Base64 => Ok(decoded_bytes),
+        TestResultFileFormat::Plain => unreachable!("handled above"),
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 struct RawTestResultUpload {
     #[serde(default)]
@@ -26,7 +83,7 @@ struct RawTestResultUpload {
     test_results_files: Vec<TestResultFile>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 struct ReadableFile {
     filename: String,
     data: Vec<u8>,
@@ -49,34 +106,119 @@ fn serialize_to_legacy_format(readable_files: Vec<ReadableFile>) -> Vec<u8> {
     res
 }
 
-// the warnings should be ordered by location because they're pushed to the vec as we parse
-// so we can guarantee that warning[x].location >= warning[x - 1].location
-// implicitly tested by warnings-junit.xml
-fn format_warnings(input: &[u8], warnings: Vec<WarningInfo>, filename: &str) -> Vec<String> {
-    let mut offset = 0;
-    let mut result = Vec::new();
-    let mut line = 1;
-    let mut col = 0;
-    let mut input_iter = input.iter();
-    for warning in warnings {
-        for bytes in input_iter
-            .by_ref()
-            .take((warning.location - offset) as usize)
-        {
-            if *bytes == b'\n' {
-                line += 1;
-                col = 0;
-            } else {
-                col += 1;
+/// Incremental decoder for the framed archive format written by `serialize_to_legacy_format`.
+///
+/// Feed it bytes as they arrive via [`LegacyFormatDecoder::decode`]; it returns each file as
+/// soon as its closing `EOF` line is seen, and keeps any trailing partial frame buffered
+/// internally rather than requiring the whole archive up front. Call
+/// [`LegacyFormatDecoder::finish`] once the input is exhausted to recover a dangling frame
+/// whose `EOF` line never arrived.
+#[derive(Debug, Default)]
+struct LegacyFormatDecoder {
+    buffer: Vec<u8>,
+}
+
+impl LegacyFormatDecoder {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `input` to the internal buffer and extracts every frame that's now complete.
+    /// Bytes belonging to an unterminated trailing frame are kept buffered for the next call
+    /// (or for [`LegacyFormatDecoder::finish`]).
+    fn decode(&mut self, input: &[u8]) -> Vec<ReadableFile> {
+        self.buffer.extend_from_slice(input);
+
+        let mut files = Vec::new();
+        let mut consumed = 0;
+        while let Some((file, frame_len)) = Self::decode_frame(&self.buffer[consumed..]) {
+            files.push(file);
+            consumed += frame_len;
+        }
+        self.buffer.drain(..consumed);
+        files
+    }
+
+    /// Recovers a dangling frame left over after the last [`LegacyFormatDecoder::decode`] call,
+    /// for an archive whose last file is missing its terminating `EOF` line. Everything read
+    /// after the filename line is treated as that file's data.
+    fn finish(self) -> Vec<ReadableFile> {
+        Self::decode_dangling_frame(&self.buffer)
+            .into_iter()
+            .collect()
+    }
+
+    /// Parses a single complete frame from the front of `buf`, returning the decoded file and
+    /// the number of bytes it occupied. Returns `None` if `buf` doesn't yet hold a full frame,
+    /// so the caller knows to wait for more bytes.
+    fn decode_frame(buf: &[u8]) -> Option<(ReadableFile, usize)> {
+        if !buf.starts_with(LEGACY_FORMAT_PREFIX) {
+            return None;
+        }
+
+        let after_prefix = &buf[LEGACY_FORMAT_PREFIX.len()..];
+        let filename_len = after_prefix.iter().position(|&b| b == b'\n')?;
+        let filename = String::from_utf8_lossy(&after_prefix[..filename_len]).into_owned();
+        let body_start = LEGACY_FORMAT_PREFIX.len() + filename_len + 1;
+
+        // Scan line by line rather than searching for the suffix as a substring, so that data
+        // which merely contains the suffix's bytes mid-line (or a line resembling
+        // `# path=...`) can't be mistaken for the frame's actual terminator.
+        let mut line_start = body_start;
+        loop {
+            let line_len = buf[line_start..].iter().position(|&b| b == b'\n')?;
+            let line = &buf[line_start..line_start + line_len];
+            if line == LEGACY_FORMAT_SUFFIX {
+                // The newline immediately preceding this line was appended by the writer as a
+                // separator, not part of the original data, so it's excluded here.
+                let data = buf[body_start..line_start - 1].to_vec();
+                let frame_len = line_start + line_len + 1;
+                return Some((ReadableFile { filename, data }, frame_len));
             }
+            line_start += line_len + 1;
         }
-        offset += warning.location;
-        result.push(format!(
-            "{} ending at {}:{} in {}",
-            warning.message, line, col, filename
-        ));
     }
-    result
+
+    /// Parses a trailing frame with no terminating `EOF` line: a filename line followed by
+    /// whatever data bytes remain. Returns `None` if `buf` is empty or doesn't even contain a
+    /// complete filename line.
+    fn decode_dangling_frame(buf: &[u8]) -> Option<ReadableFile> {
+        if buf.is_empty() || !buf.starts_with(LEGACY_FORMAT_PREFIX) {
+            return None;
+        }
+
+        let after_prefix = &buf[LEGACY_FORMAT_PREFIX.len()..];
+        let filename_len = after_prefix.iter().position(|&b| b == b'\n')?;
+        let filename = String::from_utf8_lossy(&after_prefix[..filename_len]).into_owned();
+        let body_start = LEGACY_FORMAT_PREFIX.len() + filename_len + 1;
+
+        Some(ReadableFile {
+            filename,
+            data: buf[body_start..].to_vec(),
+        })
+    }
+}
+
+/// Parses an entire framed legacy archive at once, reversing `serialize_to_legacy_format`. A
+/// thin one-shot wrapper around [`LegacyFormatDecoder`] for callers that already have the whole
+/// buffer in memory.
+fn deserialize_from_legacy_format(buf: &[u8]) -> Vec<ReadableFile> {
+    let mut decoder = LegacyFormatDecoder::new();
+    let mut files = decoder.decode(buf);
+    files.extend(decoder.finish());
+    files
+}
+
+fn format_warnings(warnings: Vec<WarningInfo>, filename: &str) -> Vec<String> {
+    warnings
+        .into_iter()
+        .map(|warning| {
+            format!(
+                "{} ending at {}:{} in {}",
+                warning.message, warning.line, warning.column, filename
+            )
+        })
+        .collect()
 }
 
 #[pyfunction]
@@ -90,36 +232,28 @@ pub fn parse_raw_upload(raw_upload_bytes: &[u8]) -> anyhow::Result<(Vec<ParsingI
     let mut readable_files: Vec<ReadableFile> = Vec::with_capacity(upload.test_results_files.len());
 
     for file in upload.test_results_files {
-        let decoded_file_bytes = BASE64_STANDARD
-            .decode(file.data)
-            .context("Error decoding base64")?;
-
-        let mut decoder = ZlibDecoder::new(decoded_file_bytes.as_slice());
-
-        let mut decompressed_file_bytes = Vec::new();
-        decoder
-            .read_to_end(&mut decompressed_file_bytes)
-            .context("Error decompressing file")?;
+        let decompressed_file_bytes = decode_file_data(&file)?;
 
         let mut reader = Reader::from_reader(decompressed_file_bytes.as_slice());
         reader.config_mut().trim_text(true);
-        let (framework, testruns, warnings) = use_reader(&mut reader, network.as_ref())
-            .with_context(|| {
-                let pos_conversion = reader.buffer_position().try_into();
-                match pos_conversion {
-                    Ok(pos) => {
-                        let (line, col) = get_position_info(&decompressed_file_bytes, pos);
-                        format!(
-                            "Error parsing JUnit XML in {} at {}:{}",
-                            file.filename, line, col
-                        )
+        let (framework, testruns, warnings) =
+            use_reader(&mut reader, network.as_ref(), &ParseOptions::default()).with_context(
+                || {
+                    let pos_conversion = reader.buffer_position().try_into();
+                    match pos_conversion {
+                        Ok(pos) => {
+                            let (line, col) = get_position_info(&decompressed_file_bytes, pos);
+                            format!(
+                                "Error parsing JUnit XML in {} at {}:{}",
+                                file.filename, line, col
+                            )
+                        }
+                        Err(_) => format!("Error parsing JUnit XML in {}", file.filename),
                     }
-                    Err(_) => format!("Error parsing JUnit XML in {}", file.filename),
-                }
-            })?;
+                },
+            )?;
 
-        let warning_strings: Vec<String> =
-            format_warnings(&decompressed_file_bytes, warnings, &file.filename);
+        let warning_strings: Vec<String> = format_warnings(warnings, &file.filename);
 
         let parsing_info = ParsingInfo {
             framework,
@@ -177,4 +311,153 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    fn test_decode_file_data_base64_compressed() {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"<xml/>").unwrap();
+        let compressed = encoder.finish().unwrap();
+        let file = TestResultFile {
+            filename: "a.xml".to_string(),
+            format: TestResultFileFormat::Base64Compressed,
+            data: BASE64_STANDARD.encode(compressed),
+        };
+        assert_eq!(decode_file_data(&file).unwrap(), b"<xml/>");
+    }
+
+    #[test]
+    fn test_decode_file_data_base64_gzip() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"<xml/>").unwrap();
+        let compressed = encoder.finish().unwrap();
+        let file = TestResultFile {
+            filename: "a.xml".to_string(),
+            format: TestResultFileFormat::Base64Gzip,
+            data: BASE64_STANDARD.encode(compressed),
+        };
+        assert_eq!(decode_file_data(&file).unwrap(), b"<xml/>");
+    }
+
+    #[test]
+    fn test_decode_file_data_base64() {
+        let file = TestResultFile {
+            filename: "a.xml".to_string(),
+            format: TestResultFileFormat::Base64,
+            data: BASE64_STANDARD.encode(b"<xml/>"),
+        };
+        assert_eq!(decode_file_data(&file).unwrap(), b"<xml/>");
+    }
+
+    #[test]
+    fn test_decode_file_data_plain() {
+        let file = TestResultFile {
+            filename: "a.xml".to_string(),
+            format: TestResultFileFormat::Plain,
+            data: "<xml/>".to_string(),
+        };
+        assert_eq!(decode_file_data(&file).unwrap(), b"<xml/>");
+    }
+
+    #[test]
+    fn test_test_result_file_format_default_is_base64_compressed() {
+        let file: TestResultFile =
+            serde_json::from_str(r#"{"filename": "a.xml", "data": ""}"#).unwrap();
+        assert_eq!(file.format, TestResultFileFormat::Base64Compressed);
+    }
+
+    #[test]
+    fn test_legacy_format_round_trip() {
+        let files = vec![
+            ReadableFile {
+                filename: "one.xml".to_string(),
+                data: b"<testsuite/>".to_vec(),
+            },
+            ReadableFile {
+                filename: "two.xml".to_string(),
+                data: b"<testsuite></testsuite>".to_vec(),
+            },
+        ];
+        let archive = serialize_to_legacy_format(files.clone());
+        assert_eq!(deserialize_from_legacy_format(&archive), files);
+    }
+
+    #[test]
+    fn test_legacy_format_round_trip_empty_data() {
+        let files = vec![ReadableFile {
+            filename: "empty.xml".to_string(),
+            data: vec![],
+        }];
+        let archive = serialize_to_legacy_format(files.clone());
+        assert_eq!(deserialize_from_legacy_format(&archive), files);
+    }
+
+    #[test]
+    fn test_legacy_format_decode_partial_buffer_waits_for_more_bytes() {
+        let archive = serialize_to_legacy_format(vec![ReadableFile {
+            filename: "one.xml".to_string(),
+            data: b"<testsuite/>".to_vec(),
+        }]);
+
+        let mut decoder = LegacyFormatDecoder::new();
+        // Feed everything except the final terminator line: no complete frame yet.
+        let split_at = archive.len() - (LEGACY_FORMAT_SUFFIX.len() + 1);
+        assert!(decoder.decode(&archive[..split_at]).is_empty());
+
+        // The rest arrives: now the frame completes.
+        let files = decoder.decode(&archive[split_at..]);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].filename, "one.xml");
+        assert_eq!(files[0].data, b"<testsuite/>");
+    }
+
+    #[test]
+    fn test_legacy_format_decode_byte_at_a_time() {
+        let files = vec![
+            ReadableFile {
+                filename: "one.xml".to_string(),
+                data: b"<testsuite/>".to_vec(),
+            },
+            ReadableFile {
+                filename: "two.xml".to_string(),
+                data: b"<testsuite></testsuite>".to_vec(),
+            },
+        ];
+        let archive = serialize_to_legacy_format(files.clone());
+
+        let mut decoder = LegacyFormatDecoder::new();
+        let mut decoded = Vec::new();
+        for byte in &archive {
+            decoded.extend(decoder.decode(std::slice::from_ref(byte)));
+        }
+        assert_eq!(decoded, files);
+    }
+
+    #[test]
+    fn test_legacy_format_missing_final_eof() {
+        let mut archive = serialize_to_legacy_format(vec![ReadableFile {
+            filename: "one.xml".to_string(),
+            data: b"<testsuite/>".to_vec(),
+        }]);
+        // Drop the terminating `EOF` line entirely, as if the upload was truncated.
+        let eof_line_len = LEGACY_FORMAT_SUFFIX.len() + 1;
+        archive.truncate(archive.len() - eof_line_len);
+
+        let files = deserialize_from_legacy_format(&archive);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].filename, "one.xml");
+        // The data retains the writer's separator newline, since there was no terminator line
+        // to tell the decoder where the real data ended.
+        assert_eq!(files[0].data, b"<testsuite/>\n");
+    }
+
+    #[test]
+    fn test_legacy_format_data_containing_marker_like_lines() {
+        let tricky_data = b"# path=not/a/real/frame\nsome <<<<<< EOF mid-line text\n".to_vec();
+        let files = vec![ReadableFile {
+            filename: "one.xml".to_string(),
+            data: tricky_data,
+        }];
+        let archive = serialize_to_legacy_format(files.clone());
+        assert_eq!(deserialize_from_legacy_format(&archive), files);
+    }
 }
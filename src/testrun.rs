@@ -1,22 +1,17 @@
+use chrono::{DateTime, Utc};
 use pyo3::prelude::*;
-use pyo3::types::PyString;
+use pyo3::types::{PyBool, PyDict, PyList, PySequence, PyString};
 use pyo3::{PyAny, PyResult};
 use serde::Serialize;
 use serde_json::Value;
 
+use crate::framework_detectors::{self, DetectionSignals, FrameworkDetection};
 use crate::validated_string::ValidatedString;
 
-static FRAMEWORKS: [(&str, Framework); 4] = [
-    ("pytest", Framework::Pytest),
-    ("vitest", Framework::Vitest),
-    ("jest", Framework::Jest),
-    ("phpunit", Framework::PHPUnit),
-];
-
-static EXTENSIONS: [(&str, Framework); 2] =
-    [(".py", Framework::Pytest), (".php", Framework::PHPUnit)];
-
-fn check_substring_before_word_boundary(string: &str, substring: &str) -> bool {
+/// Whether the first occurrence of `substring` in the lowercased `string` is immediately
+/// followed by a word boundary, e.g. `"pytest"` matches `"pytest-8.1"` but not `"pytester"`.
+/// `substring` itself is not lowercased, so it only matches when given in lowercase.
+pub fn check_substring_before_word_boundary(string: &str, substring: &str) -> bool {
     if let Some((_, suffix)) = string.to_lowercase().split_once(substring) {
         return suffix
             .chars()
@@ -26,14 +21,8 @@ fn check_substring_before_word_boundary(string: &str, substring: &str) -> bool {
     false
 }
 
-pub fn check_testsuites_name(testsuites_name: &str) -> Option<Framework> {
-    FRAMEWORKS
-        .iter()
-        .filter_map(|(name, framework)| {
-            check_substring_before_word_boundary(testsuites_name, name).then_some(*framework)
-        })
-        .next()
-}
+/// Identifies the framework from a `<testsuites>` element's `name` attribute alone.
+pub use framework_detectors::check_testsuites_name;
 
 #[derive(Clone, Copy, Debug, Serialize, PartialEq)]
 pub enum Outcome {
@@ -74,7 +63,7 @@ impl<'py> FromPyObject<'py> for Outcome {
     }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, Hash)]
 pub enum Framework {
     Pytest,
     Vitest,
@@ -113,6 +102,108 @@ impl<'py> FromPyObject<'py> for Framework {
     }
 }
 
+/// Recursively converts a parsed JSON [`Value`] into the native Python object it represents,
+/// e.g. a `Value::Object` becomes a `dict` rather than a JSON-encoded string.
+fn value_to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    match value {
+        Value::Null => Ok(py.None().into_bound(py)),
+        Value::Bool(b) => Ok(PyBool::new(py, *b).to_owned().into_any()),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(i.into_pyobject(py)?.into_any())
+            } else if let Some(u) = n.as_u64() {
+                Ok(u.into_pyobject(py)?.into_any())
+            } else {
+                let f = n.as_f64().ok_or_else(|| {
+                    PyErr::new::<pyo3::exceptions::PyValueError, _>("Invalid JSON number")
+                })?;
+                Ok(f.into_pyobject(py)?.into_any())
+            }
+        }
+        Value::String(s) => Ok(PyString::new(py, s).into_any()),
+        Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(value_to_py(py, item)?)?;
+            }
+            Ok(list.into_any())
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, value) in map {
+                dict.set_item(key, value_to_py(py, value)?)?;
+            }
+            Ok(dict.into_any())
+        }
+    }
+}
+
+/// Recursively converts a Python object into the [`Value`] it represents, by downcasting
+/// rather than stringifying it, so `bool`/`int`/`float` round-trip without losing their type.
+fn py_to_value(ob: &Bound<PyAny>) -> PyResult<Value> {
+    if ob.is_none() {
+        return Ok(Value::Null);
+    }
+
+    // Python bools are ints, so this must be checked before the int cases below.
+    if let Ok(b) = ob.downcast::<PyBool>() {
+        return Ok(Value::Bool(b.is_true()));
+    }
+    if let Ok(i) = ob.extract::<i64>() {
+        return Ok(Value::Number(i.into()));
+    }
+    if let Ok(u) = ob.extract::<u64>() {
+        return Ok(Value::Number(u.into()));
+    }
+    if let Ok(f) = ob.extract::<f64>() {
+        // JSON has no representation for NaN/Infinity; fall back to null rather than
+        // producing a `Value` that `serde_json` would refuse to serialize later.
+        return Ok(serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number));
+    }
+    if let Ok(s) = ob.downcast::<PyString>() {
+        return Ok(Value::String(s.to_string()));
+    }
+    if let Ok(seq) = ob.downcast::<PySequence>() {
+        let items = seq
+            .try_iter()?
+            .map(|item| py_to_value(&item?))
+            .collect::<PyResult<_>>()?;
+        return Ok(Value::Array(items));
+    }
+    if let Ok(dict) = ob.downcast::<PyDict>() {
+        let mut map = serde_json::Map::with_capacity(dict.len());
+        for (key, value) in dict.iter() {
+            map.insert(key.str()?.to_string(), py_to_value(&value)?);
+        }
+        return Ok(Value::Object(map));
+    }
+
+    Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+        "cannot convert this Python object into a JSON-compatible value",
+    ))
+}
+
+/// Recursively collects every string value nested inside a JSON [`Value`], so framework
+/// detection can scan structured `properties` the same way it scans flat strings elsewhere.
+/// This naturally covers well-known framework-hint keys like `framework`, `language`, `runner`,
+/// and `lang`, since their values are strings too, without needing to special-case them.
+fn collect_string_leaves<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::String(s) => out.push(s),
+        Value::Array(items) => {
+            for item in items {
+                collect_string_leaves(item, out);
+            }
+        }
+        Value::Object(map) => {
+            for value in map.values() {
+                collect_string_leaves(value, out);
+            }
+        }
+        _ => {}
+    }
+}
+
 /// Wrapper for serde_json::Value to enable PyO3 conversion
 #[derive(Clone, Debug, Serialize, PartialEq)]
 pub struct PropertiesValue(pub Option<Value>);
@@ -123,14 +214,8 @@ impl<'py> IntoPyObject<'py> for PropertiesValue {
     type Error = pyo3::PyErr;
 
     fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
-        match self.0 {
-            Some(value) => {
-                let dumped_object = serde_json::to_string(&value).map_err(|e| {
-                    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid JSON: {}", e))
-                })?;
-                let py_str = PyString::new(py, &dumped_object);
-                Ok(py_str.into_any())
-            }
+        match &self.0 {
+            Some(value) => value_to_py(py, value),
             None => Ok(py.None().into_bound(py)),
         }
     }
@@ -142,14 +227,73 @@ impl<'py> FromPyObject<'py> for PropertiesValue {
             return Ok(PropertiesValue(None));
         }
 
-        let s = ob.str()?.to_string();
-        let v: Value = serde_json::from_str(&s).map_err(|e| {
-            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid JSON: {}", e))
-        })?;
-        Ok(PropertiesValue(Some(v)))
+        Ok(PropertiesValue(Some(py_to_value(ob)?)))
+    }
+}
+
+/// The kind of retry sub-element a Surefire/Gradle-style `<testcase>` can nest.
+///
+/// `rerun*` means the test still failed after retrying; `flaky*` means it eventually passed.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+pub enum RerunKind {
+    RerunFailure,
+    RerunError,
+    FlakyFailure,
+    FlakyError,
+}
+
+impl RerunKind {
+    /// Whether this kind represents a retry that eventually passed.
+    pub fn is_flaky(self) -> bool {
+        matches!(self, RerunKind::FlakyFailure | RerunKind::FlakyError)
+    }
+}
+
+impl<'py> IntoPyObject<'py> for RerunKind {
+    type Target = PyString;
+    type Output = Bound<'py, Self::Target>;
+    type Error = std::convert::Infallible;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, std::convert::Infallible> {
+        match self {
+            RerunKind::RerunFailure => Ok("rerun_failure".into_pyobject(py)?),
+            RerunKind::RerunError => Ok("rerun_error".into_pyobject(py)?),
+            RerunKind::FlakyFailure => Ok("flaky_failure".into_pyobject(py)?),
+            RerunKind::FlakyError => Ok("flaky_error".into_pyobject(py)?),
+        }
     }
 }
 
+impl<'py> FromPyObject<'py> for RerunKind {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s = ob.extract::<&str>()?;
+        match s {
+            "rerun_failure" => Ok(RerunKind::RerunFailure),
+            "rerun_error" => Ok(RerunKind::RerunError),
+            "flaky_failure" => Ok(RerunKind::FlakyFailure),
+            "flaky_error" => Ok(RerunKind::FlakyError),
+            _ => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Invalid rerun kind: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// A single `<rerunFailure>`/`<rerunError>`/`<flakyFailure>`/`<flakyError>` occurrence
+/// nested inside a `<testcase>`.
+#[derive(IntoPyObject, FromPyObject, Clone, Debug, Serialize, PartialEq)]
+pub struct RerunInfo {
+    #[pyo3(item)]
+    pub kind: RerunKind,
+    #[pyo3(item)]
+    pub message: Option<String>,
+    #[pyo3(item)]
+    pub exception_type: Option<String>,
+    #[pyo3(item)]
+    pub stack_trace: Option<String>,
+}
+
 // i can't seem to get  pyo3(from_item_all) to work when IntoPyObject is also being derived
 #[derive(IntoPyObject, FromPyObject, Clone, Debug, Serialize, PartialEq)]
 pub struct Testrun {
@@ -173,36 +317,60 @@ pub struct Testrun {
     pub computed_name: ValidatedString,
     #[pyo3(item)]
     pub properties: PropertiesValue,
+    #[pyo3(item)]
+    pub system_out: Option<ValidatedString>,
+    #[pyo3(item)]
+    pub system_err: Option<ValidatedString>,
+    #[pyo3(item)]
+    pub reruns: Vec<RerunInfo>,
+    /// When execution of this testcase began, parsed from the nearest `timestamp` attribute
+    /// (its own, or its enclosing `<testsuite>`'s).
+    #[pyo3(item)]
+    pub timestamp: Option<DateTime<Utc>>,
 }
 
 impl Testrun {
-    pub fn framework(&self) -> Option<Framework> {
-        for (name, framework) in FRAMEWORKS {
-            if check_substring_before_word_boundary(&self.testsuite, name) {
-                return Some(framework);
-            }
-        }
+    /// Whether this testrun eventually passed after being retried.
+    pub fn is_flaky(&self) -> bool {
+        self.reruns.iter().any(|rerun| rerun.kind.is_flaky())
+    }
 
-        for (extension, framework) in EXTENSIONS {
-            if check_substring_before_word_boundary(&self.classname, extension)
-                || check_substring_before_word_boundary(&self.name, extension)
-            {
-                return Some(framework);
-            }
+    /// The number of retries this testrun went through, regardless of outcome.
+    pub fn retry_count(&self) -> usize {
+        self.reruns.len()
+    }
 
-            if let Some(message) = &self.failure_message {
-                if check_substring_before_word_boundary(message, extension) {
-                    return Some(framework);
-                }
-            }
+    /// Gathers this testrun's signals for framework detection: its testsuite name, every
+    /// string value nested in its `properties` (e.g. an explicit `framework`/`language`/
+    /// `runner`/`lang` key), the classname/name/filename that a `.py`/`.php`-style extension
+    /// might appear in, and its failure message.
+    fn detection_signals(&self) -> DetectionSignals<'_> {
+        let mut extension_sources = vec![&*self.classname, &*self.name];
+        if let Some(filename) = &self.filename {
+            extension_sources.push(&**filename);
+        }
 
-            if let Some(filename) = &self.filename {
-                if check_substring_before_word_boundary(filename, extension) {
-                    return Some(framework);
-                }
-            }
+        let mut property_values = Vec::new();
+        if let Some(properties) = &self.properties.0 {
+            collect_string_leaves(properties, &mut property_values);
         }
-        None
+
+        DetectionSignals {
+            testsuite_names: vec![&*self.testsuite],
+            property_values,
+            extension_sources,
+            messages: self.failure_message.as_deref().into_iter().collect(),
+        }
+    }
+
+    /// Runs full framework detection for this testrun, including the confidence score and any
+    /// runner-up frameworks, using the process-wide [`FrameworkRegistry`](framework_detectors::FrameworkRegistry).
+    pub fn detect_framework(&self) -> Option<FrameworkDetection> {
+        framework_detectors::detect_with_global_registry(&self.detection_signals())
+    }
+
+    pub fn framework(&self) -> Option<Framework> {
+        self.detect_framework().map(|detection| detection.framework)
     }
 }
 
@@ -247,6 +415,10 @@ mod tests {
             build_url: None,
             computed_name: ValidatedString::default(),
             properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
         };
         assert_eq!(t.framework(), Some(Framework::Pytest))
     }
@@ -264,6 +436,10 @@ mod tests {
             build_url: None,
             computed_name: ValidatedString::default(),
             properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
         };
         assert_eq!(t.framework(), Some(Framework::Pytest))
     }
@@ -281,6 +457,10 @@ mod tests {
             build_url: None,
             computed_name: ValidatedString::default(),
             properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
         };
         assert_eq!(t.framework(), Some(Framework::Pytest))
     }
@@ -298,6 +478,10 @@ mod tests {
             build_url: None,
             computed_name: ValidatedString::default(),
             properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
         };
         assert_eq!(t.framework(), Some(Framework::Pytest))
     }
@@ -315,6 +499,54 @@ mod tests {
             build_url: None,
             computed_name: ValidatedString::default(),
             properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
+        };
+        assert_eq!(t.framework(), Some(Framework::Pytest))
+    }
+
+    #[test]
+    fn test_detect_framework_properties() {
+        let t = Testrun {
+            classname: ValidatedString::default(),
+            name: ValidatedString::default(),
+            duration: None,
+            outcome: Outcome::Pass,
+            testsuite: ValidatedString::default(),
+            failure_message: None,
+            filename: None,
+            build_url: None,
+            computed_name: ValidatedString::default(),
+            properties: PropertiesValue(Some(json!({"framework": "pytest"}))),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
+        };
+        assert_eq!(t.framework(), Some(Framework::Pytest))
+    }
+
+    #[test]
+    fn test_detect_framework_nested_properties() {
+        let t = Testrun {
+            classname: ValidatedString::default(),
+            name: ValidatedString::default(),
+            duration: None,
+            outcome: Outcome::Pass,
+            testsuite: ValidatedString::default(),
+            failure_message: None,
+            filename: None,
+            build_url: None,
+            computed_name: ValidatedString::default(),
+            properties: PropertiesValue(Some(json!({
+                "ci": {"env": ["self-hosted", "pytest"]}
+            }))),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
         };
         assert_eq!(t.framework(), Some(Framework::Pytest))
     }
@@ -332,6 +564,10 @@ mod tests {
             build_url: Some("https://example.com/build_url".to_string()),
             computed_name: ValidatedString::default(),
             properties: PropertiesValue(None),
+            system_out: None,
+            system_err: None,
+            reruns: vec![],
+            timestamp: None,
         };
         assert_eq!(t.framework(), Some(Framework::Pytest))
     }
@@ -417,7 +653,6 @@ mod tests {
                 .expect("Failed to convert PropertiesValue to Python object");
             let round_trip_value = PropertiesValue::extract_bound(&property_py)
                 .expect("Failed to extract PropertiesValue from Python object");
-            // Note: booleans get converted to integers in the round trip
             assert_eq!(
                 round_trip_value,
                 PropertiesValue(Some(json!(["item1", 123, 4.25, true])))
@@ -453,7 +688,6 @@ mod tests {
                 .expect("Failed to convert PropertiesValue to Python object");
             let round_trip_value = PropertiesValue::extract_bound(&property_py)
                 .expect("Failed to extract PropertiesValue from Python object");
-            // Note: booleans get converted to integers in the round trip
             assert_eq!(
                 round_trip_value,
                 PropertiesValue(Some(json!({
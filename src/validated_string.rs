@@ -15,17 +15,23 @@ pub struct ValidatedString {
     value: String,
 }
 
+const DEFAULT_MAX_LEN: usize = 1000;
+
 impl ValidatedString {
     pub fn from_string(value: String) -> Result<Self> {
-        if value.len() > 1000 {
-            anyhow::bail!("string is too long");
-        }
-        Ok(Self { value })
+        Self::from_string_with_limit(value, DEFAULT_MAX_LEN)
     }
 
     pub fn from_str(value: &str) -> Result<Self> {
         Self::from_string(value.to_string())
     }
+
+    pub fn from_string_with_limit(value: String, max_len: usize) -> Result<Self> {
+        if value.len() > max_len {
+            anyhow::bail!("string is too long");
+        }
+        Ok(Self { value })
+    }
 }
 
 impl Deref for ValidatedString {
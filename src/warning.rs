@@ -4,10 +4,19 @@ use serde::Serialize;
 pub struct WarningInfo {
     pub message: String,
     pub location: u64,
+    /// 1-based line the warning occurred on.
+    pub line: usize,
+    /// 1-based column within `line` the warning occurred on.
+    pub column: usize,
 }
 
 impl WarningInfo {
-    pub fn new(message: String, location: u64) -> Self {
-        Self { message, location }
+    pub fn new(message: String, location: u64, line: usize, column: usize) -> Self {
+        Self {
+            message,
+            location,
+            line,
+            column,
+        }
     }
 }